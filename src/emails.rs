@@ -0,0 +1,296 @@
+use std::{
+    cmp::Reverse,
+    collections::{hash_map, BTreeMap, BTreeSet, HashMap},
+    fmt,
+    path::Path,
+    sync::Arc,
+};
+
+/// Identifies an input file by its position in processing order and its path.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Input {
+    /// Position of the input file in processing order, starting at 0.
+    pub index: usize,
+    /// Path of the input file, if the input was read from the filesystem.
+    pub path: Option<Arc<Path>>,
+}
+
+/// Describes the role of an email address within an email message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Role {
+    /// Found in the `From`, `Sender` or `Return-Path` header field.
+    Sender,
+    /// Found in the `To` or `Delivered-To` header field.
+    Recipient,
+    /// Found in the `Cc` header field.
+    Cc,
+    /// Found in the `Bcc` header field.
+    Bcc,
+    /// Found in the `Reply-To` header field.
+    ReplyTo,
+    /// Found in any other header field.
+    Header,
+    /// Found in the body of the message.
+    Body,
+}
+
+impl Role {
+    /// Returns the name of the role as used in the output.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Sender => "sender",
+            Role::Recipient => "recipient",
+            Role::Cc => "cc",
+            Role::Bcc => "bcc",
+            Role::ReplyTo => "reply-to",
+            Role::Header => "header",
+            Role::Body => "body",
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Describes where an email address was found.
+///
+/// Sources are ordered by input position, member position, member name, page, line and offset,
+/// which is used to pick the first occurrence.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Source {
+    /// Position of the input file in processing order, starting at 0.
+    pub index: usize,
+    /// Path of the input file, if the input was read from the filesystem.
+    pub path: Option<Arc<Path>>,
+    /// Positions of the archive members containing the address within their containers, outermost first.
+    pub ordinals: Arc<[usize]>,
+    /// Name of the archive member containing the address.
+    pub member: Option<Arc<str>>,
+    /// Page number of the address, starting at 1.
+    pub page: Option<usize>,
+    /// Line number of the address, starting at 1.
+    pub line: Option<usize>,
+    /// Byte offset of the address within the extracted text of the file, member or page.
+    ///
+    /// For email messages, this is the offset within the decoded header lines or within the decoded body part.
+    pub offset: u64,
+    /// Role of the address within an email message.
+    pub role: Option<Role>,
+}
+
+impl fmt::Display for Source {
+    /// Formats the source as a comma separated list of its known parts.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}", path.display())?,
+            None => write!(f, "-")?,
+        }
+        if let Some(member) = &self.member {
+            write!(f, ", member {}", member)?;
+        }
+        if let Some(page) = self.page {
+            write!(f, ", page {}", page)?;
+        }
+        if let Some(line) = self.line {
+            write!(f, ", line {}", line)?;
+        }
+        write!(f, ", offset {}", self.offset)?;
+        if let Some(role) = self.role {
+            write!(f, ", role {}", role)?;
+        }
+        Ok(())
+    }
+}
+
+/// Represents a single occurrence of an email address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Match {
+    /// The address exactly as it was found.
+    pub address: String,
+    /// The address after normalization, used for deduplication.
+    pub normalized: String,
+    /// Where the address was found.
+    pub source: Source,
+}
+
+/// Represents a deduplicated email address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// The address exactly as it was found at `source`.
+    pub address: String,
+    /// The first occurrence of the address.
+    pub source: Source,
+    /// Number of times the address was found.
+    pub count: u64,
+    /// Number of times the address was found per input file, only filled in counting mode.
+    pub files: BTreeMap<Option<Arc<Path>>, u64>,
+    /// Roles the address was found in within email messages.
+    pub roles: BTreeSet<Role>,
+}
+
+/// Represents the orders the extracted emails can be sorted in.
+///
+/// All orders are total, so the output is identical across runs on the same input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Order {
+    /// Sorts lexicographically by address.
+    #[default]
+    Lexical,
+    /// Sorts by domain, then by local part.
+    Domain,
+    /// Sorts by the first occurrence in the input.
+    FirstSeen,
+    /// Sorts by descending occurrence count, ties are broken by address.
+    Frequency,
+}
+
+/// Collection of deduplicated email addresses keyed by their normalized form.
+///
+/// Uses a HashMap to handle deduplication inherently without sorting and deduping the list explicitly.
+#[derive(Clone, Debug, Default)]
+pub struct Emails {
+    entries: HashMap<String, Entry>,
+    count_files: bool,
+    failed: usize,
+}
+
+impl Emails {
+    /// Creates an empty collection.
+    pub fn new() -> Emails {
+        Emails::default()
+    }
+
+    /// Creates an empty collection that also counts the occurrences of each address per input file.
+    pub fn with_file_counts() -> Emails {
+        Emails {
+            count_files: true,
+            ..Emails::default()
+        }
+    }
+
+    /// Adds a match to the collection, keeping the earliest source of each address.
+    pub fn insert(&mut self, m: Match) {
+        let mut files = BTreeMap::new();
+        if self.count_files {
+            files.insert(m.source.path.clone(), 1);
+        }
+        self.insert_entry(
+            m.normalized,
+            Entry {
+                address: m.address,
+                roles: m.source.role.into_iter().collect(),
+                source: m.source,
+                count: 1,
+                files,
+            },
+        );
+    }
+
+    /// Adds an entry to the collection, summing up the counts and joining the roles of duplicate addresses.
+    fn insert_entry(&mut self, normalized: String, entry: Entry) {
+        match self.entries.entry(normalized) {
+            hash_map::Entry::Occupied(mut occupied) => {
+                let existing = occupied.get_mut();
+                existing.count += entry.count;
+                for (path, count) in entry.files {
+                    *existing.files.entry(path).or_insert(0) += count;
+                }
+                existing.roles.extend(entry.roles);
+                if entry.source < existing.source {
+                    existing.address = entry.address;
+                    existing.source = entry.source;
+                }
+            }
+            hash_map::Entry::Vacant(vacant) => {
+                vacant.insert(entry);
+            }
+        }
+    }
+
+    /// Merges two collections by moving the smaller collection into the larger one.
+    pub fn merge(mut self, mut other: Emails) -> Emails {
+        if self.entries.len() < other.entries.len() {
+            std::mem::swap(&mut self, &mut other);
+        }
+        self.count_files |= other.count_files;
+        self.failed += other.failed;
+        for (normalized, entry) in other.entries {
+            self.insert_entry(normalized, entry);
+        }
+        self
+    }
+
+    /// Returns the number of distinct addresses.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks whether no address has been found.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Checks whether the given normalized address has been found.
+    pub fn contains(&self, normalized: &str) -> bool {
+        self.entries.contains_key(normalized)
+    }
+
+    /// Returns an iterator over the normalized addresses and their entries in arbitrary order.
+    pub fn iter(&self) -> hash_map::Iter<'_, String, Entry> {
+        self.entries.iter()
+    }
+
+    /// Returns the number of given input files that failed to process and were skipped.
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    /// Records a given input file that failed to process.
+    pub(crate) fn record_failure(&mut self) {
+        self.failed += 1;
+    }
+
+    /// Checks whether the occurrences of each address are counted per input file.
+    pub fn counts_files(&self) -> bool {
+        self.count_files
+    }
+
+    /// Returns the normalized addresses and their entries sorted in the given order.
+    pub fn sorted(&self, order: Order) -> Vec<(&String, &Entry)> {
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        match order {
+            Order::Lexical => entries.sort_unstable_by_key(|(email, _)| *email),
+            Order::Domain => entries.sort_unstable_by_key(|(email, _)| {
+                let (local, domain) = email.rsplit_once('@').unwrap_or(("", email));
+                (domain, local)
+            }),
+            Order::FirstSeen => {
+                entries.sort_unstable_by_key(|(email, entry)| (&entry.source, *email))
+            }
+            Order::Frequency => {
+                entries.sort_unstable_by_key(|(email, entry)| (Reverse(entry.count), *email))
+            }
+        }
+        entries
+    }
+}
+
+impl Extend<Match> for Emails {
+    fn extend<I: IntoIterator<Item = Match>>(&mut self, iter: I) {
+        for m in iter {
+            self.insert(m);
+        }
+    }
+}
+
+impl IntoIterator for Emails {
+    type Item = (String, Entry);
+    type IntoIter = hash_map::IntoIter<String, Entry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
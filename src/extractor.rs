@@ -0,0 +1,555 @@
+use crate::{
+    emails::{Emails, Input, Match, Source},
+    file::{
+        is_rejected, process_message, Compression, Context, CountingReader, FileType, Limits,
+        Location, MboxStream, Segment, TextStream, HEAD_SIZE,
+    },
+};
+use log::{error, info, warn};
+use rayon::prelude::*;
+use regex::Regex;
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Cursor, Read},
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicU64, Arc},
+};
+use tar::Archive;
+use walkdir::WalkDir;
+
+/// The default regex used to find email addresses.
+///
+/// See: https://www.regular-expressions.info/email.html for a discussion about how to find an email address.
+pub const DEFAULT_PATTERN: &str = r"(\b[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}\b)";
+
+/// Size of the pieces a large text chunk is split into for parallel scanning.
+const PIECE_SIZE: usize = 256 * 1024;
+
+/// Describes how extracted email addresses are normalized before deduplication.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Normalization {
+    /// Keeps the addresses exactly as they were found.
+    #[default]
+    None,
+    /// Lowercases the domain part, which is case-insensitive by definition.
+    Domain,
+    /// Lowercases the whole address.
+    Lowercase,
+}
+
+impl Normalization {
+    /// Returns the normalized form of the given email address.
+    pub fn apply(self, email: &str) -> String {
+        match self {
+            Normalization::None => email.to_string(),
+            Normalization::Domain => match email.rsplit_once('@') {
+                Some((local, domain)) => format!("{}@{}", local, domain.to_ascii_lowercase()),
+                None => email.to_string(),
+            },
+            Normalization::Lowercase => email.to_ascii_lowercase(),
+        }
+    }
+}
+
+/// Extracts email addresses from text, files and directories.
+///
+/// Every match carries its `Source`, the results are collected into deduplicated `Emails`.
+#[derive(Clone, Debug)]
+pub struct Extractor {
+    regex: Regex,
+    normalization: Normalization,
+    counting: bool,
+    context: Context,
+}
+
+impl Default for Extractor {
+    fn default() -> Extractor {
+        Extractor {
+            regex: Regex::new(DEFAULT_PATTERN).unwrap(), // won't panic
+            normalization: Normalization::default(),
+            counting: false,
+            context: Context::default(),
+        }
+    }
+}
+
+impl Extractor {
+    /// Creates an extractor using the `DEFAULT_PATTERN` without any normalization.
+    pub fn new() -> Extractor {
+        Extractor::default()
+    }
+
+    /// Attempts to replace the regex used to find email addresses.
+    ///
+    /// The whole match of the pattern is taken as the email address.
+    /// Streamed text is cut into chunks after bytes that can not be part of an address of the default pattern,
+    /// so a pattern matching any other characters, such as spaces, may miss a match at a chunk boundary.
+    pub fn with_pattern(self, pattern: &str) -> Result<Extractor, regex::Error> {
+        Ok(Extractor {
+            regex: Regex::new(pattern)?,
+            ..self
+        })
+    }
+
+    /// Sets how extracted email addresses are normalized.
+    pub fn with_normalization(self, normalization: Normalization) -> Extractor {
+        Extractor {
+            normalization,
+            ..self
+        }
+    }
+
+    /// Sets whether files of an unsupported MIME type are processed as plain text instead of being rejected.
+    pub fn with_unsupported_as_text(self, unsupported_as_text: bool) -> Extractor {
+        Extractor {
+            context: Context {
+                unsupported_as_text,
+                ..self.context
+            },
+            ..self
+        }
+    }
+
+    /// Sets the passwords that are tried in order against encrypted zip members and 7z archives.
+    pub fn with_passwords<I: IntoIterator<Item = String>>(self, passwords: I) -> Extractor {
+        Extractor {
+            context: Context {
+                passwords: passwords.into_iter().collect(),
+                ..self.context
+            },
+            ..self
+        }
+    }
+
+    /// Sets the limits on the data extracted from archives and compressed files.
+    pub fn with_limits(self, limits: Limits) -> Extractor {
+        Extractor {
+            context: Context {
+                limits,
+                ..self.context
+            },
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of nested archive levels that are entered.
+    pub fn with_max_depth(self, max_depth: usize) -> Extractor {
+        Extractor {
+            context: Context {
+                max_depth,
+                ..self.context
+            },
+            ..self
+        }
+    }
+
+    /// Sets whether the occurrences of each address are also counted per input file.
+    pub fn with_counting(self, counting: bool) -> Extractor {
+        Extractor { counting, ..self }
+    }
+
+    /// Finds all email addresses in the given text.
+    ///
+    /// The `input` and `location` describe where the text starts, the source of each match is derived from them.
+    pub fn find(&self, text: &str, location: &Location, input: &Input) -> Vec<Match> {
+        let ordinals: Arc<[usize]> = Arc::from(location.ordinals.as_slice());
+        let member: Option<Arc<str>> = location.member.as_deref().map(Arc::from);
+        let mut line = location.line;
+        let mut counted = 0;
+        self.regex
+            .find_iter(text)
+            .map(|m| {
+                // Counts the newlines incrementally, since matches are found in order.
+                line = line.map(|line| {
+                    line + text.as_bytes()[counted..m.start()]
+                        .iter()
+                        .filter(|&&byte| byte == b'\n')
+                        .count()
+                });
+                counted = m.start();
+                Match {
+                    address: m.as_str().to_string(),
+                    normalized: self.normalization.apply(m.as_str()),
+                    source: Source {
+                        index: input.index,
+                        path: input.path.clone(),
+                        ordinals: ordinals.clone(),
+                        member: member.clone(),
+                        page: location.page,
+                        line,
+                        offset: location.offset + m.start() as u64,
+                        role: location.role,
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Extracts email addresses from the given segments of text.
+    ///
+    /// Large segments are split at newlines and all pieces are scanned in parallel.
+    /// Each worker collects its matches into its own collection, which are merged afterwards.
+    pub fn extract(&self, segments: &[Segment], input: &Input) -> Emails {
+        let pieces: Vec<(&str, Location)> = segments
+            .iter()
+            .flat_map(|segment| split_at_newlines(&segment.text, &segment.location))
+            .collect();
+        pieces
+            .par_iter()
+            .fold(
+                || self.new_emails(),
+                |mut emails, (text, location)| {
+                    emails.extend(self.find(text, location, input));
+                    emails
+                },
+            )
+            .reduce(Emails::new, Emails::merge)
+    }
+
+    /// Attempts to detect the file type of the given bytes and extract email addresses from its content.
+    pub fn extract_bytes(&self, bytes: &[u8], input: &Input) -> io::Result<Emails> {
+        let context = self.context.for_input(&display(input));
+        let segments = context.detect(bytes)?.process(&context)?;
+        Ok(self.extract(&segments, input))
+    }
+
+    /// Attempts to extract email addresses from the given reader.
+    ///
+    /// The file type is detected from the first bytes only.
+    /// Text is streamed in chunks of bounded size and compressed data is decoded on the fly.
+    /// All other file types are read into memory as a whole.
+    pub fn extract_reader<R: Read>(&self, reader: R, input: &Input) -> io::Result<Emails> {
+        self.extract_stream(
+            Box::new(reader),
+            input,
+            &self.context.for_input(&display(input)),
+            &Location::default(),
+        )
+    }
+
+    /// Attempts to extract email addresses from the input file.
+    ///
+    /// An input without a path is read from standard input.
+    pub fn extract_file(&self, input: &Input) -> io::Result<Emails> {
+        let Some(input_path) = input.path.as_deref() else {
+            info!("Reading from standard input.");
+            let emails = self.extract_reader(io::stdin().lock(), input)?;
+            info!("Standard input processed successfully.");
+            return Ok(emails);
+        };
+        let metadata = fs::metadata(input_path)?;
+        info!("File path: {}.", input_path.display());
+        info!("File size: {} bytes.", metadata.len());
+
+        let file = File::open(input_path)?;
+        let emails = self.extract_reader(BufReader::new(file), input)?;
+        info!("File processed successfully.");
+
+        Ok(emails)
+    }
+
+    /// Attempts to extract email addresses from the file or directory at the given path.
+    pub fn extract_path(&self, input_path: &Path) -> io::Result<Emails> {
+        self.extract_paths(&[input_path])
+    }
+
+    /// Attempts to extract email addresses from all files and directories at the given paths.
+    ///
+    /// Directories are scanned recursively and the results of all files are merged into one collection.
+    /// The files are numbered in the order of the given paths and then in walk order, which defines the first occurrence of an address.
+    /// All files are processed concurrently and their results are merged afterwards.
+    ///
+    /// The path `-` stands for standard input.
+    ///
+    /// Files that fail to process are reported and skipped instead of aborting the whole run, so the addresses
+    /// of all other files are still returned. Failures of files that were given directly are counted in `Emails::failed`,
+    /// unless the file is an archive that stayed locked or exceeded one of the limits.
+    /// Only a given path that does not exist is returned as an error.
+    pub fn extract_paths<P: AsRef<Path>>(&self, input_paths: &[P]) -> io::Result<Emails> {
+        let mut files = Vec::new();
+        for input_path in input_paths {
+            let input_path = input_path.as_ref();
+            if input_path == Path::new("-") {
+                files.push((None, true));
+            } else if fs::metadata(input_path)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", input_path.display(), e)))?
+                .is_dir()
+            {
+                let found = collect_files(input_path);
+                info!("Found {} files in {}.", found.len(), input_path.display());
+                files.extend(found.into_iter().map(|file| (Some(file), false)));
+            } else {
+                files.push((Some(input_path.to_path_buf()), true));
+            }
+        }
+
+        let emails = files
+            .into_par_iter()
+            .enumerate()
+            .map(|(index, (file, given))| {
+                let input = Input {
+                    index,
+                    path: file.map(Arc::from),
+                };
+                match self.extract_file(&input) {
+                    Ok(found) => found,
+                    Err(e) if is_rejected(&e) => {
+                        warn!("Skipping {}. {}.", display(&input), e);
+                        Emails::new()
+                    }
+                    Err(e) => {
+                        error!("Failed to process {}. {}.", display(&input), e);
+                        let mut emails = Emails::new();
+                        if given {
+                            emails.record_failure();
+                        }
+                        emails
+                    }
+                }
+            })
+            .reduce(Emails::new, Emails::merge);
+        Ok(emails)
+    }
+
+    /// Attempts to extract email addresses from the given stream within the given context.
+    ///
+    /// A compressed stream is wrapped with a decoder and its payload is detected and processed the same way.
+    /// The entries of a tar stream and the messages of a mailbox are processed one after another without buffering the whole stream.
+    /// The `member` locates the archive member the stream belongs to, it has no member name for the input itself.
+    fn extract_stream<'r>(
+        &self,
+        mut reader: Box<dyn Read + 'r>,
+        input: &Input,
+        context: &Context,
+        member: &Location,
+    ) -> io::Result<Emails> {
+        let mut buffer = vec![];
+        (&mut reader)
+            .take(HEAD_SIZE as u64)
+            .read_to_end(&mut buffer)?;
+
+        let handling = match context.detect(&buffer)? {
+            FileType::Text(_) => Handling::Text,
+            FileType::Compressed(compressed_file) => {
+                Handling::Decompress(compressed_file.compression())
+            }
+            FileType::Tar(_) => Handling::Tar,
+            FileType::Mbox(_) => Handling::Mbox,
+            _ => Handling::Buffer,
+        };
+        let mut reader = Cursor::new(buffer).chain(reader);
+
+        match handling {
+            Handling::Text => {
+                let mut emails = self.new_emails();
+                let result = TextStream::new(reader).process(|mut segment| {
+                    segment.location.nest_in(member);
+                    emails = std::mem::take(&mut emails).merge(self.extract(&[segment], input));
+                });
+                keep_partial(emails, result, context, member)
+            }
+            Handling::Decompress(compression) => {
+                let context = context.enter()?;
+                let compressed = Arc::<AtomicU64>::default();
+                let decoder =
+                    compression.decoder(CountingReader::new(reader, compressed.clone()))?;
+                let reader = context.limit_stream(decoder, compressed);
+                self.extract_stream(Box::new(reader), input, &context, member)
+            }
+            Handling::Tar => {
+                let context = context.enter()?;
+                let mut archive = Archive::new(reader);
+                let mut emails = self.new_emails();
+                let result =
+                    self.extract_entries(&mut archive, input, &context, member, &mut emails);
+                keep_partial(emails, result, &context, member)
+            }
+            Handling::Mbox => {
+                let context = member_context(context, member).enter()?;
+                let mut emails = self.new_emails();
+                let result =
+                    MboxStream::new(BufReader::new(reader), context.limits.max_member_size)
+                        .process(|number, message| {
+                            let mut segments = process_message(&context, number, message)?;
+                            for segment in &mut segments {
+                                segment.location.nest_in(member);
+                            }
+                            emails =
+                                std::mem::take(&mut emails).merge(self.extract(&segments, input));
+                            Ok(())
+                        });
+                keep_partial(emails, result, &context, member)
+            }
+            Handling::Buffer => {
+                // Data nested in an archive or compressed file is subject to the limits, the input file itself is not.
+                let buffer = if context.depth > 0 {
+                    context.read_member(reader, None)?
+                } else {
+                    let mut buffer = Vec::new();
+                    reader.read_to_end(&mut buffer)?;
+                    buffer
+                };
+                let context = member_context(context, member);
+                let mut segments = context.detect(&buffer)?.process(&context)?;
+                for segment in &mut segments {
+                    segment.location.nest_in(member);
+                }
+                Ok(self.extract(&segments, input))
+            }
+        }
+    }
+
+    /// Attempts to extract email addresses from the regular files of the tar stream one after another, adding them to `emails`.
+    fn extract_entries<R: Read>(
+        &self,
+        archive: &mut Archive<R>,
+        input: &Input,
+        context: &Context,
+        member: &Location,
+        emails: &mut Emails,
+    ) -> io::Result<()> {
+        for (count, entry) in archive.entries()?.enumerate() {
+            context.check_members(count + 1)?;
+            let entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let mut location = Location::default();
+            location.nest(count, &entry.path()?.to_string_lossy());
+            location.nest_in(member);
+            match self.extract_stream(Box::new(entry), input, context, &location) {
+                Ok(found) => *emails = std::mem::take(emails).merge(found),
+                Err(e) => context.skip_member(location.member.as_deref().unwrap_or_default(), e)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates an empty collection that honors the counting mode.
+    fn new_emails(&self) -> Emails {
+        if self.counting {
+            Emails::with_file_counts()
+        } else {
+            Emails::new()
+        }
+    }
+}
+
+/// Describes how a stream is handled after its file type has been detected.
+enum Handling {
+    /// Streams the text in chunks of bounded size.
+    Text,
+    /// Decodes the compressed stream on the fly.
+    Decompress(Compression),
+    /// Processes the entries of the tar stream one after another.
+    Tar,
+    /// Processes the messages of the mailbox one after another.
+    Mbox,
+    /// Reads the whole stream into memory.
+    Buffer,
+}
+
+/// Splits the given text into pieces of roughly `PIECE_SIZE` bytes, each with its own location.
+///
+/// Pieces only end at newlines, which can never be part of an email address.
+fn split_at_newlines<'a>(text: &'a str, location: &Location) -> Vec<(&'a str, Location)> {
+    let mut pieces = Vec::new();
+    let mut location = location.clone();
+    let mut rest = text;
+    while rest.len() > PIECE_SIZE {
+        let Some(position) = rest.as_bytes()[PIECE_SIZE..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+        else {
+            break;
+        };
+        let (piece, tail) = rest.split_at(PIECE_SIZE + position + 1);
+        let next = Location {
+            line: location
+                .line
+                .map(|line| line + piece.bytes().filter(|&byte| byte == b'\n').count()),
+            offset: location.offset + piece.len() as u64,
+            ..location.clone()
+        };
+        pieces.push((piece, location));
+        location = next;
+        rest = tail;
+    }
+    pieces.push((rest, location));
+    pieces
+}
+
+/// Returns the context for processing the data of the given streamed member, which is named after it in reports.
+///
+/// Streams keep the context of the input file, as the member names of their locations already start at the input.
+fn member_context(context: &Context, member: &Location) -> Context {
+    match &member.member {
+        Some(name) => context.within(name),
+        None => context.clone(),
+    }
+}
+
+/// Keeps the addresses found in a stream before it was cut off by one of the limits.
+///
+/// The stream is reported and counted as failed, since the rest of it is not processed.
+fn keep_partial(
+    mut emails: Emails,
+    result: io::Result<()>,
+    context: &Context,
+    member: &Location,
+) -> io::Result<Emails> {
+    match result {
+        Ok(()) => Ok(emails),
+        Err(e) if is_rejected(&e) => {
+            warn!(
+                "Stopped reading {}. {}.",
+                member_context(context, member).container,
+                e
+            );
+            // A stream cut off within another one that is cut off as well still counts once.
+            if emails.failed() == 0 {
+                emails.record_failure();
+            }
+            Ok(emails)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Formats the path of the given input for log messages.
+fn display(input: &Input) -> String {
+    match &input.path {
+        Some(path) => path.display().to_string(),
+        None => String::from("-"),
+    }
+}
+
+/// Checks whether the given directory is a Maildir, which keeps its messages in the `cur` and `new` subdirectories.
+fn is_maildir(path: &Path) -> bool {
+    path.join("cur").is_dir() && path.join("new").is_dir()
+}
+
+/// Collects the paths of all files located at the given path.
+///
+/// Directories are walked recursively in file name order, a plain file yields just itself.
+/// The `tmp` subdirectory of a Maildir is skipped, as it only holds messages that are still being delivered.
+fn collect_files(input_path: &Path) -> Vec<PathBuf> {
+    WalkDir::new(input_path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            !(entry.file_type().is_dir()
+                && entry.file_name() == "tmp"
+                && entry.path().parent().is_some_and(is_maildir))
+        })
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                warn!("Skipping unreadable path. {}.", e);
+                None
+            }
+        })
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .collect()
+}
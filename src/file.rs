@@ -1,23 +1,345 @@
-use pdf_extract::extract_text_from_mem;
-use std::io::{self, Cursor, Read};
-use zip::ZipArchive;
+mod compressed;
+mod epub;
+mod mail;
+mod mbox;
+mod msg;
+mod ole;
+mod pst;
+mod rtf;
+mod sevenz;
+mod tar;
+mod xps;
+
+use crate::emails::Role;
+pub use compressed::{CompressedFile, Compression};
+pub use epub::EpubFile;
+use log::{debug, warn};
+pub use mail::MailFile;
+pub(crate) use mbox::process_message;
+pub use mbox::{MboxFile, MboxStream};
+pub use msg::MsgFile;
+pub use ole::OleFile;
+use pdf_extract::extract_text_from_mem_by_pages;
+pub use pst::PstFile;
+pub use rtf::RtfFile;
+pub use sevenz::SevenZipFile;
+use std::{
+    fmt,
+    io::{self, Cursor, Read},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+pub use tar::TarFile;
+pub use xps::XpsFile;
+use zip::{result::ZipError, ZipArchive};
 
 /// Represents different file types that can be processed.
 pub enum FileType<'a> {
     Zip(ZipFile<'a>),
     Text(TextFile<'a>),
     Pdf(PdfFile<'a>),
+    Compressed(CompressedFile<'a>),
+    Tar(TarFile<'a>),
+    SevenZip(SevenZipFile<'a>),
+    Ole(OleFile<'a>),
+    Mail(MailFile<'a>),
+    Mbox(MboxFile<'a>),
+    Msg(MsgFile<'a>),
+    Pst(PstFile<'a>),
+    Rtf(RtfFile<'a>),
+    Epub(EpubFile<'a>),
+    Xps(XpsFile<'a>),
 }
 
 /// Represents a zip file as a byte slice reference.
 pub struct ZipFile<'a>(&'a [u8]);
 
 /// Represents a text file as a byte slice reference.
-pub struct TextFile<'a>(&'a [u8]);
+pub struct TextFile<'a>(pub(crate) &'a [u8]);
 
 /// Represents a pdf file as a byte slice reference.
 pub struct PdfFile<'a>(&'a [u8]);
 
+/// Describes where within a file a piece of extracted text is located.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Location {
+    /// Positions of the archive members containing the text within their containers, starting at 0, outermost first.
+    pub ordinals: Vec<usize>,
+    /// Name of the archive member containing the text.
+    pub member: Option<String>,
+    /// Page number of the text, starting at 1.
+    pub page: Option<usize>,
+    /// Line number of the first character of the text, starting at 1.
+    pub line: Option<usize>,
+    /// Byte offset of the first character of the text within the file, member or page.
+    ///
+    /// Within email messages, the offset counts the decoded `Name: value` lines of the header fields,
+    /// while the body and every other part start again at 0, so it does not point into the raw message.
+    pub offset: u64,
+    /// Role of the text within an email message.
+    pub role: Option<Role>,
+}
+
+impl Location {
+    /// Moves the location into the archive member with the given position and name, keeping inner members.
+    ///
+    /// Nested member names are joined with `/`, e.g. `inner.zip/users.csv`.
+    pub fn nest(&mut self, ordinal: usize, name: &str) {
+        self.ordinals.insert(0, ordinal);
+        self.member = Some(match self.member.take() {
+            Some(inner) => format!("{}/{}", name, inner),
+            None => name.to_string(),
+        });
+    }
+
+    /// Moves the location into the members of the given outer location, if it has any.
+    pub fn nest_in(&mut self, outer: &Location) {
+        if let Some(name) = &outer.member {
+            self.ordinals.splice(0..0, outer.ordinals.iter().copied());
+            self.member = Some(match self.member.take() {
+                Some(inner) => format!("{}/{}", name, inner),
+                None => name.clone(),
+            });
+        }
+    }
+}
+
+/// Represents a piece of text extracted from a file together with its location.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Segment {
+    pub text: String,
+    pub location: Location,
+}
+
+/// Represents a text file as a stream that is read in chunks of bounded size.
+pub struct TextStream<R>(R);
+
+/// Number of leading bytes used to detect the file type of a stream.
+pub const HEAD_SIZE: usize = 8 * 1024;
+
+/// Number of bytes read from a `TextStream` at once.
+const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Default maximum number of nested archive levels that are entered.
+pub const DEFAULT_MAX_DEPTH: usize = 8;
+
+/// Limits that protect against archives and compressed files that expand to excessive amounts of data.
+///
+/// Data exceeding a limit is skipped and reported instead of being read into memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of bytes extracted from archives and compressed files of a single input file in total.
+    pub max_total_size: u64,
+    /// Maximum number of bytes extracted from a single archive member or compressed file.
+    pub max_member_size: u64,
+    /// Maximum ratio between the extracted and the compressed size of an archive member or compressed file.
+    pub max_ratio: u64,
+    /// Maximum number of members of a single archive.
+    pub max_members: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_total_size: 16 * 1024 * 1024 * 1024,
+            max_member_size: 1024 * 1024 * 1024,
+            max_ratio: 1000,
+            max_members: 100_000,
+        }
+    }
+}
+
+/// Settings and state shared while processing a file and everything nested inside it.
+#[derive(Clone, Debug)]
+pub struct Context {
+    /// Number of archive levels entered so far.
+    pub depth: usize,
+    /// Maximum number of nested archive levels that are entered.
+    pub max_depth: usize,
+    /// Processes data of an unsupported type as plain text instead of rejecting it.
+    pub unsupported_as_text: bool,
+    /// Passwords tried in order against encrypted archive members.
+    pub passwords: Arc<[String]>,
+    /// Limits on the data extracted from archives and compressed files.
+    pub limits: Limits,
+    /// Number of bytes extracted so far from the current input file, shared across all nesting levels.
+    pub extracted: Arc<AtomicU64>,
+    /// Path of the input file followed by the names of the archive members being processed, used in reports.
+    pub container: Arc<str>,
+}
+
+impl Default for Context {
+    fn default() -> Context {
+        Context {
+            depth: 0,
+            max_depth: DEFAULT_MAX_DEPTH,
+            unsupported_as_text: false,
+            passwords: Arc::default(),
+            limits: Limits::default(),
+            extracted: Arc::default(),
+            container: Arc::from("-"),
+        }
+    }
+}
+
+impl Context {
+    /// Returns a copy of the context with its own count of extracted bytes, used for the input file at the given path.
+    pub fn for_input(&self, path: &str) -> Context {
+        Context {
+            extracted: Arc::default(),
+            container: Arc::from(path),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of the context for processing the archive member with the given name.
+    pub fn within(&self, name: &str) -> Context {
+        Context {
+            container: Arc::from(format!("{}/{}", self.container, name)),
+            ..self.clone()
+        }
+    }
+
+    /// Attempts to enter one more archive level, failing if `max_depth` would be exceeded.
+    pub fn enter(&self) -> io::Result<Context> {
+        if self.depth >= self.max_depth {
+            return Err(exceeded(format!(
+                "Maximum nesting depth of {} exceeded",
+                self.max_depth
+            )));
+        }
+        Ok(Context {
+            depth: self.depth + 1,
+            ..self.clone()
+        })
+    }
+
+    /// Attempts to convert the given bytes into a `FileType`, honoring `unsupported_as_text`.
+    pub fn detect<'a>(&self, bytes: &'a [u8]) -> io::Result<FileType<'a>> {
+        match bytes.try_into_filetype() {
+            Err(e) if self.unsupported_as_text && e.kind() == io::ErrorKind::Unsupported => {
+                debug!("{}. Processing as plain text.", e);
+                Ok(FileType::Text(TextFile(bytes)))
+            }
+            result => result,
+        }
+    }
+
+    /// Attempts to admit an archive with the given number of members, failing if `max_members` is exceeded.
+    pub fn check_members(&self, count: usize) -> io::Result<()> {
+        if count > self.limits.max_members {
+            return Err(exceeded(format!(
+                "Archive has more than {} members",
+                self.limits.max_members
+            )));
+        }
+        Ok(())
+    }
+
+    /// Attempts to read the data of an archive member or compressed file, enforcing the size limits.
+    ///
+    /// The `compressed_size` of the data, if known, is used to enforce the compression ratio.
+    /// At most one byte more than allowed is read, so the memory used stays bounded even if the data is hostile.
+    pub fn read_member<R: Read>(
+        &self,
+        reader: R,
+        compressed_size: Option<u64>,
+    ) -> io::Result<Vec<u8>> {
+        let limits = &self.limits;
+        let remaining = limits
+            .max_total_size
+            .saturating_sub(self.extracted.load(Ordering::Relaxed));
+        let ratio_size = compressed_size.map(|size| size.max(1).saturating_mul(limits.max_ratio));
+        let limit = limits
+            .max_member_size
+            .min(remaining)
+            .min(ratio_size.unwrap_or(u64::MAX));
+
+        let mut buffer = Vec::new();
+        reader
+            .take(limit.saturating_add(1))
+            .read_to_end(&mut buffer)?;
+        let size = buffer.len() as u64;
+        self.extracted.fetch_add(size, Ordering::Relaxed);
+        if size <= limit {
+            return Ok(buffer);
+        }
+        Err(limit_error(limits, size > remaining, size))
+    }
+
+    /// Wraps the decoded stream of a compressed file, enforcing the compression ratio against the `compressed` size read so far.
+    pub(crate) fn limit_stream<R: Read>(
+        &self,
+        reader: R,
+        compressed: Arc<AtomicU64>,
+    ) -> LimitedReader<R> {
+        LimitedReader {
+            reader,
+            max_ratio: self.limits.max_ratio,
+            compressed,
+            size: 0,
+        }
+    }
+
+    /// Checks whether the total extracted size of the current input file has been exceeded.
+    pub fn exhausted(&self) -> bool {
+        self.extracted.load(Ordering::Relaxed) > self.limits.max_total_size
+    }
+
+    /// Attempts to report and skip an archive member that failed to process.
+    ///
+    /// Once the total extracted size of the input file is exceeded, every following member would fail as well,
+    /// so the error is returned instead to abandon the whole input file.
+    pub fn skip_member(&self, name: &str, e: io::Error) -> io::Result<()> {
+        if self.exhausted() {
+            return Err(e);
+        }
+        report_skipped(&self.container, name, &e);
+        Ok(())
+    }
+
+    /// Attempts to process the bytes of an archive member according to their detected `FileType`.
+    ///
+    /// The member position and name are prepended to those of all returned segments.
+    /// Members that fail to process are reported and skipped, so one broken member does not spoil the whole archive.
+    pub fn process_member(
+        &self,
+        ordinal: usize,
+        name: &str,
+        bytes: &[u8],
+    ) -> io::Result<Vec<Segment>> {
+        let context = self.within(name);
+        match context
+            .detect(bytes)
+            .and_then(|file_type| file_type.process(&context))
+        {
+            Ok(mut segments) => {
+                for segment in &mut segments {
+                    segment.location.nest(ordinal, name);
+                }
+                Ok(segments)
+            }
+            Err(e) => {
+                self.skip_member(name, e)?;
+                Ok(Vec::new())
+            }
+        }
+    }
+}
+
+/// Reports an archive member of the given container that is skipped because it failed to process.
+///
+/// Members of an unsupported type, such as images inside office documents, are expected and only logged for debugging.
+pub(crate) fn report_skipped(container: &str, name: &str, e: &io::Error) {
+    if e.kind() == io::ErrorKind::Unsupported {
+        debug!("Skipping member {} of {}. {}.", name, container, e);
+    } else {
+        warn!("Skipping member {} of {}. {}.", name, container, e);
+    }
+}
+
 impl<'a> AsRef<[u8]> for ZipFile<'a> {
     /// Converts a `ZipFile` to its byte slice reference.
     fn as_ref(&self) -> &[u8] {
@@ -27,57 +349,310 @@ impl<'a> AsRef<[u8]> for ZipFile<'a> {
 
 /// Trait for processing different file types.
 pub trait ProcessFile<'a> {
-    /// Attempts to process the given byte slice and return a vector of strings containing the extracted text.
-    fn process(&'a self) -> io::Result<Vec<String>>;
+    /// Attempts to process the given byte slice and return a vector of segments containing the extracted text.
+    ///
+    /// The `context` carries the settings and nesting state of the surrounding processing.
+    fn process(&'a self, context: &Context) -> io::Result<Vec<Segment>>;
 }
 
 impl<'a> ProcessFile<'a> for ZipFile<'a> {
-    /// Attempts to parse a given byte slice as a zip archive and extracts the content of its xml files as strings.
-    fn process(&'a self) -> io::Result<Vec<String>> {
+    /// Attempts to parse a given byte slice as a zip archive and processes each of its members.
+    ///
+    /// Every member is fed back through the file type detection, so archives may contain any supported file type,
+    /// including further archives up to the maximum nesting depth.
+    fn process(&'a self, context: &Context) -> io::Result<Vec<Segment>> {
+        let context = context.enter()?;
         // Makes the byte slice readable by wrapping it with Cursor.
         let reader = Cursor::new(self.0);
         let mut archive = ZipArchive::new(reader)?;
-        let mut xml = Vec::new();
+        context.check_members(archive.len())?;
+        let mut segments = Vec::new();
+        // Index of the password that decrypted the previous member, which is tried first for the next one.
+        let mut known = None;
         // Step through each file contained in the archive.
         for i in 0..archive.len() {
-            let mut file = archive.by_index(i)?;
-            // Ensures we only read valid UTF-8 streams.
-            if file.name().ends_with(".xml") {
-                let mut buffer = String::new();
-                file.read_to_string(&mut buffer)?;
-                xml.push(buffer);
+            // Reads the raw entry first, as the metadata is available without decrypting.
+            let (name, encrypted, compressed_size) = {
+                let file = archive.by_index_raw(i)?;
+                if file.is_dir() {
+                    continue;
+                }
+                (
+                    file.name().to_string(),
+                    file.encrypted(),
+                    file.compressed_size(),
+                )
+            };
+            let buffer = if encrypted {
+                decrypt_member(&mut archive, i, &context, known).map(|(buffer, index)| {
+                    known = Some(index);
+                    buffer
+                })
+            } else {
+                context.read_member(archive.by_index(i)?, Some(compressed_size))
+            };
+            match buffer {
+                Ok(buffer) => segments.extend(context.process_member(i, &name, &buffer)?),
+                Err(e) => context.skip_member(&name, e)?,
             }
         }
-        Ok(xml)
+        Ok(segments)
+    }
+}
+
+/// Attempts to read the encrypted zip member at the given index with each of the passwords in turn.
+///
+/// The password at index `known` is tried first. Returns the decrypted bytes and the index of the matching password.
+/// ZipCrypto may accept a wrong password by chance, which is caught by the checksum while reading,
+/// or by one of the limits if the garbage happens to inflate. A limit is therefore only reported
+/// if none of the other passwords decrypts the member either.
+fn decrypt_member(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    index: usize,
+    context: &Context,
+    known: Option<usize>,
+) -> io::Result<(Vec<u8>, usize)> {
+    let passwords = &context.passwords;
+    let candidates = known
+        .into_iter()
+        .chain((0..passwords.len()).filter(|&i| Some(i) != known));
+    let mut rejection = None;
+    for candidate in candidates {
+        match archive.by_index_decrypt(index, passwords[candidate].as_bytes()) {
+            Ok(file) => {
+                let compressed_size = file.compressed_size();
+                let extracted = context.extracted.load(Ordering::Relaxed);
+                match context.read_member(file, Some(compressed_size)) {
+                    Ok(buffer) => return Ok((buffer, candidate)),
+                    Err(e) => {
+                        // The garbage read with a wrong password does not count as extracted.
+                        context.extracted.store(extracted, Ordering::Relaxed);
+                        if is_rejected(&e) {
+                            rejection.get_or_insert(e);
+                        }
+                    }
+                }
+            }
+            Err(ZipError::InvalidPassword) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(rejection.unwrap_or_else(|| locked(passwords.len())))
+}
+
+/// Error payload for data that is deliberately not processed.
+#[derive(Debug)]
+enum Rejection {
+    /// Encrypted data that none of the given number of passwords could decrypt.
+    Locked(usize),
+    /// Data that exceeds one of the limits, as described by the message.
+    Exceeded(String),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Locked(0) => write!(f, "Locked, no password given"),
+            Rejection::Locked(1) => write!(f, "Locked, the password did not match"),
+            Rejection::Locked(n) => write!(f, "Locked, none of the {} passwords matched", n),
+            Rejection::Exceeded(message) => write!(f, "{}", message),
+        }
     }
 }
 
+impl std::error::Error for Rejection {}
+
+/// Creates the error reported for encrypted data that none of the given number of passwords could decrypt.
+pub(crate) fn locked(passwords: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        Rejection::Locked(passwords),
+    )
+}
+
+/// Creates the error reported for data that exceeds one of the limits.
+pub(crate) fn exceeded(message: String) -> io::Error {
+    io::Error::other(Rejection::Exceeded(message))
+}
+
+/// Creates the error reported for data of the given size, naming the limit it exceeds.
+fn limit_error(limits: &Limits, total_exceeded: bool, size: u64) -> io::Error {
+    if total_exceeded {
+        exceeded(format!(
+            "Total extracted size exceeds the limit of {} bytes",
+            limits.max_total_size
+        ))
+    } else if size > limits.max_member_size {
+        exceeded(format!(
+            "Member size exceeds the limit of {} bytes",
+            limits.max_member_size
+        ))
+    } else {
+        ratio_error(limits.max_ratio)
+    }
+}
+
+/// Creates the error reported for data that expands beyond the given compression ratio.
+fn ratio_error(max_ratio: u64) -> io::Error {
+    exceeded(format!(
+        "Compression ratio exceeds the limit of {}",
+        max_ratio
+    ))
+}
+
+/// Counts the bytes read from a stream, such as the compressed size of a file while it is decoded.
+pub(crate) struct CountingReader<R> {
+    reader: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R> CountingReader<R> {
+    /// Wraps the given reader, adding the number of bytes read to the given count.
+    pub(crate) fn new(reader: R, count: Arc<AtomicU64>) -> CountingReader<R> {
+        CountingReader { reader, count }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.count.fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+/// Streams a decoded compressed file, failing once it expands beyond the compression ratio.
+///
+/// The size limits do not apply, since the stream is processed piece by piece instead of being read into memory.
+pub(crate) struct LimitedReader<R> {
+    reader: R,
+    max_ratio: u64,
+    compressed: Arc<AtomicU64>,
+    size: u64,
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.size += read as u64;
+        let compressed = self.compressed.load(Ordering::Relaxed);
+        if self.size > compressed.max(1).saturating_mul(self.max_ratio) {
+            return Err(ratio_error(self.max_ratio));
+        }
+        Ok(read)
+    }
+}
+
+/// Checks whether the error was caused by data that is locked or exceeds one of the limits.
+pub(crate) fn is_rejected(e: &io::Error) -> bool {
+    e.get_ref().is_some_and(|inner| inner.is::<Rejection>())
+}
+
 impl<'a> ProcessFile<'a> for TextFile<'a> {
-    /// Converts a given byte slice to a string and splits it into lines.
+    /// Converts a given byte slice to a string starting at the first line.
     ///
     /// Invalid UTF-8 sequences get replaced with �.
-    fn process(&'a self) -> io::Result<Vec<String>> {
-        Ok(String::from_utf8_lossy(self.0)
-            .lines()
-            .map(String::from)
-            .collect())
+    fn process(&'a self, _context: &Context) -> io::Result<Vec<Segment>> {
+        Ok(vec![Segment {
+            text: String::from_utf8_lossy(self.0).into_owned(),
+            location: Location {
+                line: Some(1),
+                ..Location::default()
+            },
+        }])
     }
 }
 
 impl<'a> ProcessFile<'a> for PdfFile<'a> {
-    /// Attempts to parse the given byte slice as a pdf file and extract its text.
-    fn process(&'a self) -> io::Result<Vec<String>> {
-        extract_text_from_mem(self.0)
-            .map(|text| vec![text])
-            .map_err(|err| {
-                io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("Failed to extract PDF text. {}.", err),
-                )
+    /// Attempts to parse the given byte slice as a pdf file and extract its text page by page.
+    fn process(&'a self, _context: &Context) -> io::Result<Vec<Segment>> {
+        extract_text_from_mem_by_pages(self.0)
+            .map(|pages| {
+                pages
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, text)| Segment {
+                        text,
+                        location: Location {
+                            page: Some(i + 1),
+                            ..Location::default()
+                        },
+                    })
+                    .collect()
             })
+            .map_err(|err| io::Error::other(format!("Failed to extract PDF text. {}.", err)))
     }
 }
 
+impl<R: Read> TextStream<R> {
+    /// Wraps the given reader as a text stream.
+    pub fn new(reader: R) -> TextStream<R> {
+        TextStream(reader)
+    }
+
+    /// Attempts to read the stream chunk by chunk and pass each chunk as a segment to `f`.
+    ///
+    /// Memory usage stays bounded by `CHUNK_SIZE` regardless of the stream length.
+    /// The line number and byte offset of each segment continue where the previous chunk ended.
+    ///
+    /// A chunk always ends at an ASCII byte that can not be part of an email address.
+    /// The remaining bytes are carried over to the next chunk, so no address is cut in half at a chunk boundary.
+    ///
+    /// Invalid UTF-8 sequences get replaced with �.
+    /// If reading fails, the bytes read so far are still passed to `f` before the error is returned.
+    pub fn process(mut self, mut f: impl FnMut(Segment)) -> io::Result<()> {
+        let mut buffer = Vec::with_capacity(CHUNK_SIZE);
+        let mut location = Location {
+            line: Some(1),
+            ..Location::default()
+        };
+        let mut emit = |bytes: &[u8]| {
+            let segment = Segment {
+                text: String::from_utf8_lossy(bytes).into_owned(),
+                location: location.clone(),
+            };
+            location.line = location
+                .line
+                .map(|line| line + bytes.iter().filter(|&&byte| byte == b'\n').count());
+            location.offset += bytes.len() as u64;
+            f(segment);
+        };
+        loop {
+            let filled = buffer.len();
+            let read = match (&mut self.0)
+                .take((CHUNK_SIZE - filled.min(CHUNK_SIZE)) as u64)
+                .read_to_end(&mut buffer)
+            {
+                Ok(read) => read,
+                Err(e) => {
+                    if !buffer.is_empty() {
+                        emit(&buffer);
+                    }
+                    return Err(e);
+                }
+            };
+            if read == 0 {
+                if !buffer.is_empty() {
+                    emit(&buffer);
+                }
+                return Ok(());
+            }
+            // Falls back to the whole chunk for pathological input without any separator.
+            let end = buffer
+                .iter()
+                .rposition(|&byte| byte.is_ascii() && !is_email_byte(byte))
+                .map_or(buffer.len(), |position| position + 1);
+            emit(&buffer[..end]);
+            buffer.drain(..end);
+        }
+    }
+}
+
+/// Checks whether the given byte may appear within an email address.
+fn is_email_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'.' | b'_' | b'%' | b'+' | b'-' | b'@')
+}
+
 // Implementing `TryFrom` provides an equivalent `TryInto` implementation for free.
 impl<'a> TryFrom<&'a [u8]> for FileType<'a> {
     type Error = io::Error;
@@ -85,13 +660,24 @@ impl<'a> TryFrom<&'a [u8]> for FileType<'a> {
     /// Attempts to convert the given byte buffer into a supported `FileType` without panicking.
     ///
     /// Many file formats are actually zip archives containing other files such as xml.
-    /// 
-    /// For unknown MIME types we default to plain text assuming they contain valid UTF-8.
+    ///
+    /// For unknown MIME types we default to plain text assuming they contain valid UTF-8, unless the text is an email message or mailbox.
     ///
     /// Supported:
     ///     - plain text (e.g. txt, csv, sql, json, xml, html)
-    ///     - zip archives containing xml (e.g. odp, ods, odt, docx, xlsx)
+    ///     - zip archives containing any supported file type (e.g. zip, odp, ods, odt, docx, xlsx)
     ///     - pdf files
+    ///     - rich text documents (rtf)
+    ///     - EPUB e-books, of which the chapters are processed
+    ///     - XPS and OpenXPS documents, of which the pages are processed
+    ///     - gzip, bzip2, xz and zstd compressed files containing any supported file type
+    ///     - tar archives containing any supported file type
+    ///     - 7z archives containing any supported file type
+    ///     - legacy office documents stored as OLE compound files (e.g. doc, xls, ppt)
+    ///     - email messages (eml), including their attachments
+    ///     - mailboxes in mbox format containing email messages
+    ///     - Outlook messages (msg), including their attachments
+    ///     - Outlook data files (pst, ost) containing messages
     fn try_from(bytes: &'a [u8]) -> io::Result<FileType<'a>> {
         if let Some(t) = infer::get(bytes) {
             match t.mime_type() {
@@ -99,15 +685,48 @@ impl<'a> TryFrom<&'a [u8]> for FileType<'a> {
                 | "application/vnd.oasis.opendocument.presentation" // odp
                 | "application/vnd.oasis.opendocument.spreadsheet" // ods
                 | "application/vnd.oasis.opendocument.text" // odt
-                | "application/msword" // docx
-                | "application/zip" => Ok(FileType::Zip(ZipFile(bytes))),
+                | "application/vnd.openxmlformats-officedocument.wordprocessingml.document" // docx
+                | "application/vnd.openxmlformats-officedocument.presentationml.presentation" // pptx
+                | "application/zip" => {
+                    // XPS documents are plain zip archives to the detection, recognized by their fixed document sequence.
+                    if xps::is_xps(bytes) {
+                        Ok(FileType::Xps(XpsFile(bytes)))
+                    } else {
+                        Ok(FileType::Zip(ZipFile(bytes)))
+                    }
+                }
+                "application/epub+zip" => Ok(FileType::Epub(EpubFile(bytes))),
                 "application/pdf" => Ok(FileType::Pdf(PdfFile(bytes))),
+                "application/rtf" => Ok(FileType::Rtf(RtfFile(bytes))),
+                "application/gzip" => Ok(FileType::Compressed(CompressedFile(bytes, Compression::Gzip))),
+                "application/x-bzip2" => Ok(FileType::Compressed(CompressedFile(bytes, Compression::Bzip2))),
+                "application/x-xz" => Ok(FileType::Compressed(CompressedFile(bytes, Compression::Xz))),
+                "application/zstd" => Ok(FileType::Compressed(CompressedFile(bytes, Compression::Zstd))),
+                "application/x-tar" => Ok(FileType::Tar(TarFile(bytes))),
+                "application/x-7z-compressed" => Ok(FileType::SevenZip(SevenZipFile(bytes))),
+                "application/msword" // doc
+                | "application/vnd.ms-excel" // xls
+                | "application/vnd.ms-powerpoint" // ppt
+                | "application/x-ole-storage" => {
+                    // Outlook messages are compound files as well, recognized by their property stream.
+                    if msg::is_msg(bytes) {
+                        Ok(FileType::Msg(MsgFile(bytes)))
+                    } else {
+                        Ok(FileType::Ole(OleFile(bytes)))
+                    }
+                }
                 "text/html" | "text/xml" => Ok(FileType::Text(TextFile(bytes))),
                 mime_type => Err(io::Error::new(
                     io::ErrorKind::Unsupported,
                     format!("Unsupported file type: {}", mime_type),
                 )),
             }
+        } else if pst::is_pst(bytes) {
+            Ok(FileType::Pst(PstFile(bytes)))
+        } else if mbox::is_mbox(bytes) {
+            Ok(FileType::Mbox(MboxFile(bytes)))
+        } else if mail::is_mail(bytes) {
+            Ok(FileType::Mail(MailFile(bytes)))
         } else {
             Ok(FileType::Text(TextFile(bytes)))
         }
@@ -118,11 +737,22 @@ impl<'a> FileType<'a> {
     /// Attempts to process the different `FileType` variants.
     ///
     /// Extracts available text content from the files.
-    pub fn process(self) -> io::Result<Vec<String>> {
+    pub fn process(self, context: &Context) -> io::Result<Vec<Segment>> {
         match self {
-            FileType::Text(text_file) => text_file.process(),
-            FileType::Zip(zip_file) => zip_file.process(),
-            FileType::Pdf(pdf_file) => pdf_file.process(),
+            FileType::Text(text_file) => text_file.process(context),
+            FileType::Zip(zip_file) => zip_file.process(context),
+            FileType::Pdf(pdf_file) => pdf_file.process(context),
+            FileType::Compressed(compressed_file) => compressed_file.process(context),
+            FileType::Tar(tar_file) => tar_file.process(context),
+            FileType::SevenZip(sevenz_file) => sevenz_file.process(context),
+            FileType::Ole(ole_file) => ole_file.process(context),
+            FileType::Mail(mail_file) => mail_file.process(context),
+            FileType::Mbox(mbox_file) => mbox_file.process(context),
+            FileType::Msg(msg_file) => msg_file.process(context),
+            FileType::Pst(pst_file) => pst_file.process(context),
+            FileType::Rtf(rtf_file) => rtf_file.process(context),
+            FileType::Epub(epub_file) => epub_file.process(context),
+            FileType::Xps(xps_file) => xps_file.process(context),
         }
     }
 }
@@ -140,3 +770,45 @@ impl<'a> TryIntoFileType<'a> for &'a [u8] {
         self.try_into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collects the segments of a text stream over the given bytes.
+    fn stream(bytes: Vec<u8>) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        TextStream::new(Cursor::new(bytes))
+            .process(|segment| segments.push(segment))
+            .unwrap();
+        segments
+    }
+
+    #[test]
+    fn keeps_address_straddling_chunk_boundary_whole() {
+        let address = "alice@example.com";
+        let mut bytes = "filler line\n".repeat(CHUNK_SIZE / 12).into_bytes();
+        bytes.truncate(CHUNK_SIZE - address.len() / 2);
+        bytes.extend_from_slice(format!(" {}\nlast line\n", address).as_bytes());
+
+        let segments = stream(bytes.clone());
+        assert_eq!(segments.len(), 2);
+        assert!(segments[1].text.starts_with(address));
+        assert_eq!(segments[1].location.offset, segments[0].text.len() as u64);
+        let lines = segments[0].text.matches('\n').count();
+        assert_eq!(segments[1].location.line, Some(lines + 1));
+        let text: String = segments
+            .iter()
+            .map(|segment| segment.text.as_str())
+            .collect();
+        assert_eq!(text.as_bytes(), bytes.as_slice());
+    }
+
+    #[test]
+    fn cuts_chunk_without_separator_at_chunk_size() {
+        let segments = stream(vec![b'a'; CHUNK_SIZE + 10]);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].text.len(), CHUNK_SIZE);
+        assert_eq!(segments[1].location.offset, CHUNK_SIZE as u64);
+    }
+}
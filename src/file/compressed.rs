@@ -0,0 +1,56 @@
+use super::{Context, ProcessFile, Segment};
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use lzma_rust2::XzReader;
+use ruzstd::StreamingDecoder;
+use std::io::{self, Read};
+
+/// Represents the compression formats that are decompressed transparently.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// Attempts to wrap the given reader with a streaming decoder for the compression format.
+    ///
+    /// Concatenated gzip, bzip2 and xz streams are decoded as one continuous payload.
+    pub fn decoder<'r, R: Read + 'r>(self, reader: R) -> io::Result<Box<dyn Read + 'r>> {
+        Ok(match self {
+            Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+            Compression::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+            Compression::Xz => Box::new(XzReader::new(reader, true)),
+            Compression::Zstd => Box::new(StreamingDecoder::new(reader).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Failed to decode zstd frame. {}", err),
+                )
+            })?),
+        })
+    }
+}
+
+/// Represents a compressed file as a byte slice reference and its compression format.
+pub struct CompressedFile<'a>(pub(crate) &'a [u8], pub(crate) Compression);
+
+impl<'a> CompressedFile<'a> {
+    /// Returns the compression format of the file.
+    pub fn compression(&self) -> Compression {
+        self.1
+    }
+}
+
+impl<'a> ProcessFile<'a> for CompressedFile<'a> {
+    /// Attempts to decompress the given byte slice and process the payload according to its detected file type.
+    ///
+    /// Decompression counts as one nesting level, so stacked compression layers are bounded as well.
+    /// The payload is subject to the same size and ratio limits as an archive member.
+    fn process(&'a self, context: &Context) -> io::Result<Vec<Segment>> {
+        let context = context.enter()?;
+        let payload = context.read_member(self.1.decoder(self.0)?, Some(self.0.len() as u64))?;
+        context.detect(&payload)?.process(&context)
+    }
+}
@@ -0,0 +1,223 @@
+use super::{Context, Location, ProcessFile, Segment};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use log::debug;
+use roxmltree::{Document, ParsingOptions};
+use std::{
+    borrow::Cow,
+    io::{self, Cursor},
+};
+use zip::ZipArchive;
+
+/// Represents an EPUB e-book, which is a zip archive of html documents, as a byte slice reference.
+pub struct EpubFile<'a>(pub(crate) &'a [u8]);
+
+/// Path of the container document, which points to the package document of the book.
+const CONTAINER: &str = "META-INF/container.xml";
+
+/// Elements whose text runs on within the surrounding text instead of starting a line of its own.
+const INLINE: [&str; 14] = [
+    "a", "abbr", "b", "bdi", "cite", "code", "em", "i", "kbd", "small", "span", "strong", "sub",
+    "sup",
+];
+
+/// Attempts to read the archive member at the given path, enforcing the size limits.
+pub(super) fn read_entry(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    path: &str,
+    context: &Context,
+) -> io::Result<Vec<u8>> {
+    let file = archive.by_name(path)?;
+    let compressed_size = file.compressed_size();
+    context.read_member(file, Some(compressed_size))
+}
+
+/// Attempts to decode the given bytes as the text of an xml document.
+///
+/// UTF-16 documents are recognized by their byte order mark, or by the start of their xml declaration without one.
+/// All other documents have to be UTF-8.
+pub(super) fn xml_text(bytes: &[u8]) -> io::Result<Cow<'_, str>> {
+    let (encoding, start) = match Encoding::for_bom(bytes) {
+        Some(found) => found,
+        None if bytes.starts_with(b"<\0?\0") => (UTF_16LE, 0),
+        None if bytes.starts_with(b"\0<\0?") => (UTF_16BE, 0),
+        None => (UTF_8, 0),
+    };
+    encoding
+        .decode_without_bom_handling_and_without_replacement(&bytes[start..])
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid {} in xml document", encoding.name()),
+            )
+        })
+}
+
+/// Attempts to parse the given text as an xml document.
+pub(super) fn parse_xml<'t>(text: &'t str, what: &str) -> io::Result<Document<'t>> {
+    Document::parse(text).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Malformed {}: {}", what, e),
+        )
+    })
+}
+
+/// Extracts the text of a chapter, which is an xhtml document, decoding character and entity references.
+///
+/// Block elements start a new line, so the text of adjacent paragraphs does not run together.
+/// The addresses of `mailto:` links are added on lines of their own, while scripts and styles are dropped.
+/// Chapters that are not well-formed, such as those using html entities, are taken as raw markup instead.
+fn chapter_text(bytes: &[u8], path: &str) -> String {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let text = String::from_utf8_lossy(bytes);
+    let document = match Document::parse_with_options(&text, options) {
+        Ok(document) => document,
+        Err(e) => {
+            debug!(
+                "Malformed chapter {}: {}. Processing as raw markup.",
+                path, e
+            );
+            return text.into_owned();
+        }
+    };
+    let mut chapter = String::new();
+    for node in document.descendants() {
+        if node.is_text() {
+            let skipped = node
+                .ancestors()
+                .any(|node| node.has_tag_name("script") || node.has_tag_name("style"));
+            if !skipped {
+                chapter.push_str(node.text().unwrap_or_default());
+            }
+        } else if node.is_element() {
+            if !INLINE.contains(&node.tag_name().name()) {
+                chapter.push('\n');
+            }
+            if let Some(address) = node.attribute("href").and_then(mailto_address) {
+                chapter.push('\n');
+                chapter.push_str(&address);
+                chapter.push('\n');
+            }
+        }
+    }
+    chapter
+}
+
+/// Decodes the percent-encoded characters of a URL or a part of it, such as `%40` for `@`.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Returns the decoded address a `mailto:` link points to, or `None` for other links.
+pub(super) fn mailto_address(link: &str) -> Option<String> {
+    let address = link
+        .get(..7)?
+        .eq_ignore_ascii_case("mailto:")
+        .then(|| &link[7..])?;
+    Some(percent_decode(address))
+}
+
+/// Resolves a reference to an archive member against the directory of the referring document.
+///
+/// Fragments are removed, percent-encoded characters decoded and `.` and `..` segments resolved.
+/// References starting with `/` are relative to the root of the archive.
+pub(super) fn resolve(directory: &str, href: &str) -> String {
+    let href = percent_decode(href.split('#').next().unwrap_or_default());
+    let directory = if href.starts_with('/') { "" } else { directory };
+    let mut segments: Vec<&str> = directory.split('/').filter(|s| !s.is_empty()).collect();
+    for segment in href.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+impl<'a> ProcessFile<'a> for EpubFile<'a> {
+    /// Attempts to parse the given byte slice as an EPUB e-book and processes its package document and its chapters.
+    ///
+    /// The chapters are the documents of the spine, whose text is extracted in reading order
+    /// and named by their path in the archive. Stylesheets, fonts and images are skipped.
+    fn process(&'a self, context: &Context) -> io::Result<Vec<Segment>> {
+        let context = context.enter()?;
+        let mut archive = ZipArchive::new(Cursor::new(self.0))?;
+        context.check_members(archive.len())?;
+
+        let container = read_entry(&mut archive, CONTAINER, &context)?;
+        let package_path = parse_xml(&xml_text(&container)?, "EPUB container")?
+            .descendants()
+            .find(|node| node.has_tag_name("rootfile"))
+            .and_then(|node| node.attribute("full-path"))
+            .map(str::to_string)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "EPUB container names no package document",
+                )
+            })?;
+        let package = read_entry(&mut archive, &package_path, &context)?;
+        let directory = package_path
+            .rsplit_once('/')
+            .map_or("", |(directory, _)| directory);
+
+        let chapters: Vec<String> = {
+            let text = xml_text(&package)?;
+            let document = parse_xml(&text, "EPUB package document")?;
+            let manifest: Vec<(&str, &str)> = document
+                .descendants()
+                .filter(|node| node.has_tag_name("item"))
+                .filter_map(|node| Some((node.attribute("id")?, node.attribute("href")?)))
+                .collect();
+            document
+                .descendants()
+                .filter(|node| node.has_tag_name("itemref"))
+                .filter_map(|node| node.attribute("idref"))
+                .filter_map(|idref| manifest.iter().find(|(id, _)| *id == idref))
+                .map(|(_, href)| resolve(directory, href))
+                .collect()
+        };
+
+        // The metadata of the package document, such as the publisher, may hold addresses as well.
+        let mut segments = context.process_member(0, &package_path, &package)?;
+        for (i, chapter) in chapters.into_iter().enumerate() {
+            match read_entry(&mut archive, &chapter, &context) {
+                Ok(bytes) => {
+                    let mut location = Location::default();
+                    location.nest(i + 1, &chapter);
+                    segments.push(Segment {
+                        text: chapter_text(&bytes, &chapter),
+                        location,
+                    });
+                }
+                Err(e) => context.skip_member(&chapter, e)?,
+            }
+        }
+        Ok(segments)
+    }
+}
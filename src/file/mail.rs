@@ -0,0 +1,320 @@
+use super::{Context, Location, ProcessFile, Segment, HEAD_SIZE};
+use crate::emails::Role;
+use mail_parser::{Address, HeaderValue, Message, MessageParser, MimeHeaders, PartType};
+use std::io;
+
+/// Represents an email message in Internet Message Format (eml) as a byte slice reference.
+pub struct MailFile<'a>(pub(crate) &'a [u8]);
+
+/// Header fields of which at least two have to be present to recognize a message.
+const KNOWN_HEADERS: [&str; 13] = [
+    "from",
+    "to",
+    "cc",
+    "subject",
+    "date",
+    "message-id",
+    "received",
+    "return-path",
+    "mime-version",
+    "delivered-to",
+    "reply-to",
+    "sender",
+    "content-type",
+];
+
+/// Checks whether the given bytes start with a block of message header fields.
+///
+/// Every line up to the first empty line has to be a header field or the continuation of one,
+/// and at least two of them have to be common message header fields.
+pub(crate) fn is_mail(bytes: &[u8]) -> bool {
+    let head = &bytes[..bytes.len().min(HEAD_SIZE)];
+    let mut lines: Vec<&[u8]> = head.split(|&byte| byte == b'\n').collect();
+    // The last line may be cut off by the head size, even in the middle of a header name.
+    if !head.ends_with(b"\n") {
+        lines.pop();
+    }
+    let mut known = 0;
+    for (i, line) in lines.into_iter().enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            break;
+        }
+        if i > 0 && matches!(line[0], b' ' | b'\t') {
+            continue;
+        }
+        let Some(colon) = line.iter().position(|&byte| byte == b':') else {
+            return false;
+        };
+        let name = &line[..colon];
+        if name.is_empty() || !name.iter().all(u8::is_ascii_graphic) {
+            return false;
+        }
+        if KNOWN_HEADERS
+            .iter()
+            .any(|known| name.eq_ignore_ascii_case(known.as_bytes()))
+        {
+            known += 1;
+        }
+    }
+    known >= 2
+}
+
+/// Formats a list of addresses as `Name <address>` separated by commas.
+fn format_addresses(address: &Address) -> String {
+    let addresses: Vec<&mail_parser::Addr> = match address {
+        Address::List(list) => list.iter().collect(),
+        Address::Group(groups) => groups
+            .iter()
+            .flat_map(|group| group.addresses.iter())
+            .collect(),
+    };
+    addresses
+        .iter()
+        .map(|addr| match (&addr.name, &addr.address) {
+            (Some(name), Some(address)) => format!("{} <{}>", name, address),
+            (Some(name), None) => name.to_string(),
+            (None, Some(address)) => address.to_string(),
+            (None, None) => String::new(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Returns the role of the addresses in the header field with the given name.
+///
+/// Returns `None` for fields holding message identifiers, which look like addresses but are not.
+fn header_role(name: &str) -> Option<Role> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "message-id" | "resent-message-id" | "references" | "in-reply-to" | "content-id" => {
+            return None
+        }
+        "from" | "sender" | "return-path" | "resent-from" | "resent-sender" => Role::Sender,
+        "to" | "resent-to" | "delivered-to" | "x-original-to" | "envelope-to" => Role::Recipient,
+        "cc" | "resent-cc" => Role::Cc,
+        "bcc" | "resent-bcc" => Role::Bcc,
+        "reply-to" => Role::ReplyTo,
+        _ => Role::Header,
+    })
+}
+
+/// Formats each header field of a message as a decoded `Name: value` line tagged with the role of its addresses.
+///
+/// Encoded words are decoded, values without a textual form are taken from the raw message.
+/// Fields holding message identifiers are left out.
+fn header_segments(message: &Message) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut offset = 0;
+    for header in message.headers() {
+        let Some(role) = header_role(header.name()) else {
+            continue;
+        };
+        let value = match header.value() {
+            HeaderValue::Address(address) => format_addresses(address),
+            HeaderValue::Text(value) => value.to_string(),
+            HeaderValue::TextList(values) => values.join(", "),
+            _ => String::from_utf8_lossy(
+                message
+                    .raw_message()
+                    .get(header.offset_start()..header.offset_end())
+                    .unwrap_or_default(),
+            )
+            .trim()
+            .to_string(),
+        };
+        let text = format!("{}: {}\n", header.name(), value);
+        let length = text.len() as u64;
+        segments.push(Segment {
+            text,
+            location: Location {
+                offset,
+                role: Some(role),
+                ..Location::default()
+            },
+        });
+        offset += length;
+    }
+    segments
+}
+
+/// Parses a block of raw header fields, such as the internet headers kept by Outlook, into segments placed at `offset`.
+pub(super) fn raw_header_segments(headers: &str, offset: u64) -> Vec<Segment> {
+    let headers = format!("{}\r\n\r\n", headers);
+    let Some(message) = MessageParser::default().parse_headers(headers.as_bytes()) else {
+        return Vec::new();
+    };
+    let mut segments = header_segments(&message);
+    for segment in &mut segments {
+        segment.location.offset += offset;
+    }
+    segments
+}
+
+/// Attempts to extract the text of a parsed message, its parts and everything attached to it.
+///
+/// The text of header fields and of the body is tagged with the role of the addresses found in it.
+/// Of the alternatives of a body, the HTML version is left out if there is a plain text version,
+/// so the addresses of the body are not counted twice.
+/// Attachments are fed back through the file type detection and named by their file name, or by their part number.
+/// Attached messages are processed the same way as the message itself.
+fn message_segments(message: &Message, context: &Context) -> io::Result<Vec<Segment>> {
+    let mut segments = header_segments(message);
+    for (i, part) in message.parts.iter().enumerate() {
+        let name = match part.attachment_name() {
+            Some(name) => name.to_string(),
+            None => format!("part {}", i + 1),
+        };
+        match &part.body {
+            // The parser lists a plain text alternative in place of the HTML version, if there is one.
+            PartType::Html(_)
+                if part.attachment_name().is_none() && !message.text_body.contains(&i) => {}
+            PartType::Text(text) | PartType::Html(text) => {
+                let mut segment = Segment {
+                    text: text.to_string(),
+                    ..Segment::default()
+                };
+                // Text attachments are named like other attachments, only the text of the message itself is its body.
+                match part.attachment_name() {
+                    Some(_) => segment.location.nest(i, &name),
+                    None => segment.location.role = Some(Role::Body),
+                }
+                segments.push(segment);
+            }
+            PartType::Binary(bytes) | PartType::InlineBinary(bytes) => {
+                segments.extend(context.process_member(i, &name, bytes)?);
+            }
+            PartType::Message(attached) => {
+                match context
+                    .within(&name)
+                    .enter()
+                    .and_then(|context| message_segments(attached, &context))
+                {
+                    Ok(found) => segments.extend(found.into_iter().map(|mut segment| {
+                        segment.location.nest(i, &name);
+                        segment
+                    })),
+                    Err(e) => context.skip_member(&name, e)?,
+                }
+            }
+            PartType::Multipart(_) => {}
+        }
+    }
+    Ok(segments)
+}
+
+impl<'a> ProcessFile<'a> for MailFile<'a> {
+    /// Attempts to parse the given byte slice as an email message and extracts the text of its headers and parts.
+    ///
+    /// Header fields are decoded as described in RFC 2047, and bodies are decoded from base64 or quoted-printable
+    /// and converted from their character set, so no address is hidden by the transfer encoding.
+    fn process(&'a self, context: &Context) -> io::Result<Vec<Segment>> {
+        let context = context.enter()?;
+        let message = MessageParser::default().parse(self.0).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "Failed to parse email message")
+        })?;
+        message_segments(&message, &context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::mbox::is_mbox;
+
+    /// Builds a message whose header block is cut by the head size in the middle of a `Received` header name,
+    /// given the number of bytes in front of the message.
+    ///
+    /// The body is quoted-printable and hides the address `hidden@example.com`.
+    fn long_headers(prefix: usize) -> Vec<u8> {
+        let mut message = b"From: sender@example.com\r\nTo: recipient@example.com\r\n".to_vec();
+        let line = format!("X-Padding: {}\r\n", "a".repeat(100));
+        while message.len() + 2 * line.len() < HEAD_SIZE {
+            message.extend_from_slice(line.as_bytes());
+        }
+        // Fills the head up to the cut with one more header line.
+        let fill = HEAD_SIZE - 2 - prefix - message.len() - "X-Fill: \r\n".len();
+        message.extend_from_slice(format!("X-Fill: {}\r\n", "a".repeat(fill)).as_bytes());
+        message.extend_from_slice(
+            b"Received: from relay\r\nContent-Type: text/plain\r\n\
+            Content-Transfer-Encoding: quoted-printable\r\n\r\nWrite to hidden=40example.com\r\n",
+        );
+        message
+    }
+
+    /// Extracts the addresses of the given message as a stream, detecting its type from the head.
+    fn extract(message: &[u8]) -> crate::Emails {
+        crate::Extractor::new()
+            .extract_reader(message, &crate::Input::default())
+            .unwrap()
+    }
+
+    /// Returns the number of occurrences and the roles of the given address.
+    fn found(emails: &crate::Emails, address: &str) -> Option<(u64, Vec<Role>)> {
+        emails
+            .iter()
+            .find(|(email, _)| *email == address)
+            .map(|(_, entry)| (entry.count, entry.roles.iter().copied().collect()))
+    }
+
+    #[test]
+    fn detects_mail_cut_inside_header_name() {
+        let message = long_headers(0);
+        let head = &message[..HEAD_SIZE];
+        assert!(head.ends_with(b"\r\nRe"));
+        assert!(is_mail(head));
+    }
+
+    #[test]
+    fn detects_mbox_cut_inside_header_name() {
+        let mut mailbox = b"From sender@example.com Mon Jan  1 00:00:00 2024\n".to_vec();
+        mailbox.extend_from_slice(&long_headers(mailbox.len()));
+        let head = &mailbox[..HEAD_SIZE];
+        assert!(head.ends_with(b"\r\nRe"));
+        assert!(is_mbox(head));
+    }
+
+    #[test]
+    fn decodes_message_with_headers_beyond_head_size() {
+        let emails = extract(&long_headers(0));
+        assert_eq!(
+            found(&emails, "hidden@example.com"),
+            Some((1, vec![Role::Body]))
+        );
+        assert_eq!(
+            found(&emails, "sender@example.com"),
+            Some((1, vec![Role::Sender]))
+        );
+        assert_eq!(
+            found(&emails, "recipient@example.com"),
+            Some((1, vec![Role::Recipient]))
+        );
+    }
+
+    #[test]
+    fn prefers_plain_text_alternative() {
+        let message = b"From: sender@example.com\r\n\
+            Content-Type: multipart/alternative; boundary=b\r\n\r\n\
+            --b\r\nContent-Type: text/plain\r\n\r\nWrite to body@example.com\r\n\
+            --b\r\nContent-Type: text/html\r\n\r\n<p>Write to <b>body@example.com</b></p>\r\n\
+            --b--\r\n";
+        assert_eq!(
+            found(&extract(message), "body@example.com"),
+            Some((1, vec![Role::Body]))
+        );
+    }
+
+    #[test]
+    fn keeps_html_body_without_alternative() {
+        let message = b"From: sender@example.com\r\nContent-Type: text/html\r\n\r\n\
+            <p>Write to <b>body@example.com</b></p>\r\n";
+        assert_eq!(
+            found(&extract(message), "body@example.com"),
+            Some((1, vec![Role::Body]))
+        );
+    }
+
+    #[test]
+    fn rejects_text_with_colons() {
+        assert!(!is_mail(b"Note: this is text\nnot a header line\n"));
+    }
+}
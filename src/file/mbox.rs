@@ -0,0 +1,147 @@
+use super::{exceeded, mail::is_mail, Context, MailFile, ProcessFile, Segment};
+use std::io::{self, BufRead, Read};
+
+/// Represents a mailbox in mbox format as a byte slice reference.
+pub struct MboxFile<'a>(pub(crate) &'a [u8]);
+
+/// Represents a mailbox in mbox format as a stream that is read message by message.
+pub struct MboxStream<R> {
+    reader: R,
+    max_message_size: u64,
+}
+
+/// Checks whether the given bytes start with an mbox separator line followed by a message.
+pub(crate) fn is_mbox(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"From ")
+        && bytes
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .is_some_and(|end| is_mail(&bytes[end + 1..]))
+}
+
+/// Attempts to process a single message of a mailbox, named by its number, starting at 1.
+///
+/// A message that fails to process or could not be read is reported and skipped, like an archive member.
+pub(crate) fn process_message(
+    context: &Context,
+    number: usize,
+    message: io::Result<&[u8]>,
+) -> io::Result<Vec<Segment>> {
+    let name = format!("message {}", number);
+    match message.and_then(|message| MailFile(message).process(&context.within(&name))) {
+        Ok(mut segments) => {
+            for segment in &mut segments {
+                segment.location.nest(number - 1, &name);
+            }
+            Ok(segments)
+        }
+        Err(e) => {
+            context.skip_member(&name, e)?;
+            Ok(Vec::new())
+        }
+    }
+}
+
+/// Checks whether the line is a `From ` line quoted by one or more `>`, as escaped in the body of a message.
+fn is_quoted_from(line: &[u8]) -> bool {
+    let quotes = line.iter().take_while(|&&byte| byte == b'>').count();
+    quotes > 0 && line[quotes..].starts_with(b"From ")
+}
+
+/// Returns the message read from a mailbox, or the error for a message that exceeded the maximum size.
+fn read_message(message: &[u8], oversized: bool, max_size: u64) -> io::Result<&[u8]> {
+    if oversized {
+        return Err(exceeded(format!(
+            "Message size exceeds the limit of {} bytes",
+            max_size
+        )));
+    }
+    Ok(message)
+}
+
+impl<R: BufRead> MboxStream<R> {
+    /// Wraps the given reader as an mbox stream whose messages may hold at most `max_message_size` bytes.
+    pub fn new(reader: R, max_message_size: u64) -> MboxStream<R> {
+        MboxStream {
+            reader,
+            max_message_size,
+        }
+    }
+
+    /// Attempts to read the stream message by message and pass each message with its number, starting at 1, to `f`.
+    ///
+    /// A message starts after a line beginning with `From ` at the start of the stream or after an empty line.
+    /// Only one message is held in memory at a time, so mailboxes of any size can be processed.
+    /// A message exceeding the maximum size is read past and passed as an error instead.
+    /// Lines quoted as `>From ` lose one `>`, which undoes the escaping of mboxrd mailboxes.
+    pub fn process(
+        mut self,
+        mut f: impl FnMut(usize, io::Result<&[u8]>) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let max_size = self.max_message_size;
+        let mut message = Vec::new();
+        let mut line = Vec::new();
+        let mut number = 0;
+        let mut oversized = false;
+        let mut after_empty_line = true;
+        // Whether the next read starts a new line, or continues a line that was too long to be read at once.
+        let mut line_start = true;
+        let mut in_separator = false;
+        loop {
+            line.clear();
+            // Reads at most one byte more than the message may still hold, so a single line can not exhaust the memory.
+            let limit = max_size.saturating_sub(message.len() as u64);
+            if (&mut self.reader)
+                .take(limit.saturating_add(1))
+                .read_until(b'\n', &mut line)?
+                == 0
+            {
+                break;
+            }
+            let starts_line = std::mem::replace(&mut line_start, line.ends_with(b"\n"));
+            if !starts_line && in_separator {
+                continue;
+            }
+            if starts_line && after_empty_line && line.starts_with(b"From ") {
+                if number > 0 {
+                    f(number, read_message(&message, oversized, max_size))?;
+                }
+                number += 1;
+                message.clear();
+                oversized = false;
+                after_empty_line = false;
+                in_separator = true;
+                continue;
+            }
+            in_separator = false;
+            after_empty_line = starts_line && matches!(line.as_slice(), b"\n" | b"\r\n");
+            if !oversized {
+                let unquoted = starts_line && is_quoted_from(&line);
+                message.extend_from_slice(&line[usize::from(unquoted)..]);
+                if message.len() as u64 > max_size {
+                    oversized = true;
+                    message.clear();
+                }
+            }
+        }
+        if number > 0 {
+            f(number, read_message(&message, oversized, max_size))?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ProcessFile<'a> for MboxFile<'a> {
+    /// Attempts to split the given byte slice into its messages and processes each of them as an email message.
+    ///
+    /// Every message is named by its number in the mailbox, starting at 1.
+    fn process(&'a self, context: &Context) -> io::Result<Vec<Segment>> {
+        let context = context.enter()?;
+        let mut segments = Vec::new();
+        MboxStream::new(self.0, context.limits.max_member_size).process(|number, message| {
+            segments.extend(process_message(&context, number, message)?);
+            Ok(())
+        })?;
+        Ok(segments)
+    }
+}
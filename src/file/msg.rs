@@ -0,0 +1,226 @@
+use super::{
+    mail::raw_header_segments,
+    ole::{decode_latin1, decode_utf16, read_stream, u32_at},
+    Context, Location, ProcessFile, Segment,
+};
+use crate::emails::Role;
+use cfb::CompoundFile;
+use std::io::{self, Cursor};
+
+/// Represents an Outlook message (msg), which is stored as an OLE compound file, as a byte slice reference.
+pub struct MsgFile<'a>(pub(crate) &'a [u8]);
+
+type Storage<'a> = CompoundFile<Cursor<&'a [u8]>>;
+
+/// Name of the stream holding the fixed size properties of a message, recipient or attachment.
+const PROPERTIES: &str = "__properties_version1.0";
+
+/// Size of the header preceding the fixed size properties of a recipient or attachment.
+const OBJECT_HEADER_SIZE: usize = 8;
+
+/// Properties of a message that are extracted as header fields, with the role of the addresses they contain.
+pub(super) const MESSAGE_FIELDS: [(u16, &str, Role); 11] = [
+    (0x0042, "Sent-Representing-Name", Role::Sender),
+    (0x0065, "Sent-Representing-Email", Role::Sender),
+    (0x5D02, "Sent-Representing-Smtp-Address", Role::Sender),
+    (0x0C1A, "Sender-Name", Role::Sender),
+    (0x0C1F, "Sender-Email", Role::Sender),
+    (0x5D01, "Sender-Smtp-Address", Role::Sender),
+    (0x0E04, "Display-To", Role::Recipient),
+    (0x0E03, "Display-Cc", Role::Cc),
+    (0x0E02, "Display-Bcc", Role::Bcc),
+    (0x0050, "Reply-Recipient-Names", Role::ReplyTo),
+    (0x0037, "Subject", Role::Header),
+];
+
+/// Checks whether the given bytes are a compound file holding the properties of an Outlook message.
+pub(crate) fn is_msg(bytes: &[u8]) -> bool {
+    CompoundFile::open(Cursor::new(bytes))
+        .is_ok_and(|file| file.is_stream(format!("/{}", PROPERTIES)))
+}
+
+/// Reads a string property of the given storage, which is stored either as UTF-16 or as single byte characters.
+///
+/// Returns `None` if the property is missing or empty.
+fn string_property(file: &mut Storage, storage: &str, id: u16) -> Option<String> {
+    let text = [0x001F, 0x001E].into_iter().find_map(|kind| {
+        let path = format!("{}/__substg1.0_{:04X}{:04X}", storage, id, kind);
+        if !file.is_stream(&path) {
+            return None;
+        }
+        let bytes = read_stream(file, &path).ok()?;
+        Some(match kind {
+            0x001F => decode_utf16(&bytes),
+            _ => decode_latin1(&bytes),
+        })
+    })?;
+    let text = text.trim_end_matches('\0').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Reads a 32-bit integer property of the recipient or attachment in the given storage from its fixed size properties.
+fn integer_property(file: &mut Storage, storage: &str, id: u16) -> Option<u32> {
+    let properties = read_stream(file, &format!("{}/{}", storage, PROPERTIES)).ok()?;
+    let tag = (id as u32) << 16 | 0x0003;
+    properties
+        .get(OBJECT_HEADER_SIZE..)?
+        .chunks_exact(16)
+        .find(|entry| u32_at(entry, 0) == Some(tag))
+        .and_then(|entry| u32_at(entry, 8))
+}
+
+/// Returns the paths of the child storages of the given storage whose names start with `prefix`, in order.
+fn child_storages(file: &Storage, storage: &str, prefix: &str) -> io::Result<Vec<String>> {
+    let mut paths: Vec<String> = file
+        .read_storage(if storage.is_empty() { "/" } else { storage })?
+        .filter(|entry| entry.is_storage() && entry.name().starts_with(prefix))
+        .map(|entry| format!("{}/{}", storage, entry.name()))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Appends a header field to the segments, placed after the previous fields.
+pub(super) fn push_field(
+    segments: &mut Vec<Segment>,
+    offset: &mut u64,
+    name: &str,
+    value: &str,
+    role: Role,
+) {
+    let text = format!("{}: {}\n", name, value);
+    let length = text.len() as u64;
+    segments.push(Segment {
+        text,
+        location: Location {
+            offset: *offset,
+            role: Some(role),
+            ..Location::default()
+        },
+    });
+    *offset += length;
+}
+
+/// Appends a recipient as a header field named after its recipient type, reading its string properties with `property`.
+///
+/// The display name, the email address and the SMTP address are joined, as they differ for Exchange recipients.
+pub(super) fn push_recipient(
+    segments: &mut Vec<Segment>,
+    offset: &mut u64,
+    kind: Option<u32>,
+    property: impl FnMut(u16) -> Option<String>,
+) {
+    let (name, role) = match kind {
+        Some(2) => ("Cc", Role::Cc),
+        Some(3) => ("Bcc", Role::Bcc),
+        _ => ("To", Role::Recipient),
+    };
+    let values: Vec<String> = [0x3001, 0x3003, 0x39FE]
+        .into_iter()
+        .filter_map(property)
+        .collect();
+    if !values.is_empty() {
+        push_field(segments, offset, name, &values.join(", "), role);
+    }
+}
+
+/// Returns the body of a message as a segment, which is the plain text body, or the HTML body returned by `html`.
+///
+/// The HTML body is only a fallback, as it usually repeats the plain text body.
+pub(super) fn body_segment(
+    text: Option<String>,
+    html: impl FnOnce() -> Option<String>,
+) -> Option<Segment> {
+    text.or_else(html).map(|text| Segment {
+        text,
+        location: Location {
+            role: Some(Role::Body),
+            ..Location::default()
+        },
+    })
+}
+
+/// Attempts to extract the text of the message in the given storage, its recipients and everything attached to it.
+///
+/// The sender, the recipients and the subject are extracted as header fields tagged with the role of their addresses,
+/// followed by the internet headers the message was received with, if any.
+/// Attached files are fed back through the file type detection and named by their file name, or by their number.
+/// Embedded messages are processed the same way as the message itself.
+fn message_segments(
+    file: &mut Storage,
+    storage: &str,
+    context: &Context,
+) -> io::Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut offset = 0;
+    for (id, name, role) in MESSAGE_FIELDS {
+        if let Some(value) = string_property(file, storage, id) {
+            push_field(&mut segments, &mut offset, name, &value, role);
+        }
+    }
+
+    for recipient in child_storages(file, storage, "__recip_version1.0_")? {
+        let kind = integer_property(file, &recipient, 0x0C15);
+        push_recipient(&mut segments, &mut offset, kind, |id| {
+            string_property(file, &recipient, id)
+        });
+    }
+
+    // The headers of a received message are kept as they came in, which may reveal further addresses.
+    if let Some(headers) = string_property(file, storage, 0x007D) {
+        segments.extend(raw_header_segments(&headers, offset));
+    }
+
+    let text = string_property(file, storage, 0x1000);
+    segments.extend(body_segment(text, || {
+        let path = format!("{}/__substg1.0_10130102", storage);
+        let bytes = read_stream(file, &path).ok()?;
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }));
+
+    for (i, attachment) in child_storages(file, storage, "__attach_version1.0_")?
+        .into_iter()
+        .enumerate()
+    {
+        let name = [0x3707, 0x3704, 0x3001]
+            .into_iter()
+            .find_map(|id| string_property(file, &attachment, id))
+            .unwrap_or_else(|| format!("attachment {}", i + 1));
+        let data = format!("{}/__substg1.0_37010102", attachment);
+        let embedded = format!("{}/__substg1.0_3701000D", attachment);
+        if file.is_stream(&data) {
+            match file
+                .open_stream(&data)
+                .and_then(|stream| context.read_member(stream, None))
+            {
+                Ok(bytes) => segments.extend(context.process_member(i, &name, &bytes)?),
+                Err(e) => context.skip_member(&name, e)?,
+            }
+        } else if file.is_stream(format!("{}/{}", embedded, PROPERTIES)) {
+            match context
+                .within(&name)
+                .enter()
+                .and_then(|context| message_segments(file, &embedded, &context))
+            {
+                Ok(found) => segments.extend(found.into_iter().map(|mut segment| {
+                    segment.location.nest(i, &name);
+                    segment
+                })),
+                Err(e) => context.skip_member(&name, e)?,
+            }
+        }
+    }
+    Ok(segments)
+}
+
+impl<'a> ProcessFile<'a> for MsgFile<'a> {
+    /// Attempts to parse the given byte slice as an Outlook message and extracts the text of its properties and attachments.
+    ///
+    /// Messages are read from their MAPI property streams, so the sender and every recipient are found
+    /// even if the message has no internet headers.
+    fn process(&'a self, context: &Context) -> io::Result<Vec<Segment>> {
+        let context = context.enter()?;
+        let mut file = CompoundFile::open(Cursor::new(self.0))?;
+        message_segments(&mut file, "", &context)
+    }
+}
@@ -0,0 +1,381 @@
+use super::{exceeded, Context, Limits, ProcessFile, Segment};
+use cfb::CompoundFile;
+use std::io::{self, Cursor, Read};
+
+/// Represents an OLE compound file, such as a legacy Word, Excel or PowerPoint document, as a byte slice reference.
+pub struct OleFile<'a>(pub(crate) &'a [u8]);
+
+/// Creates the error reported for malformed streams of a compound file.
+fn malformed(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Malformed {} stream", what),
+    )
+}
+
+/// Attempts to read the whole stream at the given path of the compound file.
+pub(super) fn read_stream(
+    file: &mut CompoundFile<Cursor<&[u8]>>,
+    path: &str,
+) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    file.open_stream(path)?.read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Reads a little endian `u16` at the given offset, if in bounds.
+pub(super) fn u16_at(bytes: &[u8], offset: usize) -> Option<u16> {
+    let bytes = bytes.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// Reads a little endian `u32` at the given offset, if in bounds.
+pub(super) fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Decodes UTF-16LE bytes, replacing invalid sequences with �.
+pub(super) fn decode_utf16(bytes: &[u8]) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Decodes single byte characters, which cover ASCII and Latin-1.
+pub(super) fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| char::from(byte)).collect()
+}
+
+/// Collects the strings of a document, failing once their total size exceeds the member size limit.
+///
+/// The records of a document may refer to the same characters over and over, so the text can grow far beyond the file.
+struct Strings {
+    strings: Vec<String>,
+    size: u64,
+    max_size: u64,
+}
+
+impl Strings {
+    /// Creates an empty collection limited by the member size limit.
+    fn new(limits: &Limits) -> Strings {
+        Strings {
+            strings: Vec::new(),
+            size: 0,
+            max_size: limits.max_member_size,
+        }
+    }
+
+    /// Attempts to add a string, failing if the text would exceed the limit.
+    fn push(&mut self, string: String) -> io::Result<()> {
+        self.size += string.len() as u64;
+        if self.size > self.max_size {
+            return Err(exceeded(format!(
+                "Document text exceeds the limit of {} bytes",
+                self.max_size
+            )));
+        }
+        self.strings.push(string);
+        Ok(())
+    }
+
+    /// Joins the strings with the given separator.
+    fn join(self, separator: &str) -> String {
+        self.strings.join(separator)
+    }
+}
+
+/// Attempts to extract the text of a Word 97-2003 document from its piece table.
+///
+/// The piece table in the table stream maps the text positions to runs of either single byte or UTF-16 characters
+/// in the `WordDocument` stream.
+fn word_text(file: &mut CompoundFile<Cursor<&[u8]>>, limits: &Limits) -> io::Result<String> {
+    let document = read_stream(file, "/WordDocument")?;
+    let flags = u16_at(&document, 0x0A).ok_or_else(|| malformed("WordDocument"))?;
+    if flags & 0x0100 != 0 {
+        return Err(io::Error::other(
+            "Encrypted Word documents are not supported",
+        ));
+    }
+    let table = read_stream(
+        file,
+        if flags & 0x0200 != 0 {
+            "/1Table"
+        } else {
+            "/0Table"
+        },
+    )?;
+
+    // Position and size of the piece table within the table stream.
+    let fc_clx = u32_at(&document, 0x01A2).ok_or_else(|| malformed("WordDocument"))? as usize;
+    let lcb_clx = u32_at(&document, 0x01A6).ok_or_else(|| malformed("WordDocument"))? as usize;
+    let mut clx = table
+        .get(fc_clx..fc_clx.saturating_add(lcb_clx))
+        .ok_or_else(|| malformed("table"))?;
+
+    // Skips the property modifiers preceding the piece table.
+    while clx.first() == Some(&0x01) {
+        let size = u16_at(clx, 1).ok_or_else(|| malformed("table"))? as usize;
+        clx = clx.get(3 + size..).ok_or_else(|| malformed("table"))?;
+    }
+    if clx.first() != Some(&0x02) {
+        return Err(malformed("table"));
+    }
+    let size = u32_at(clx, 1).ok_or_else(|| malformed("table"))? as usize;
+    let plc = clx.get(5..5 + size).ok_or_else(|| malformed("table"))?;
+
+    // The piece table holds n + 1 character positions followed by n piece descriptors of 8 bytes.
+    let pieces = size.saturating_sub(4) / 12;
+    let mut text = Strings::new(limits);
+    for i in 0..pieces {
+        let start = u32_at(plc, i * 4).ok_or_else(|| malformed("table"))? as usize;
+        let end = u32_at(plc, (i + 1) * 4).ok_or_else(|| malformed("table"))? as usize;
+        let fc = u32_at(plc, (pieces + 1) * 4 + i * 8 + 2).ok_or_else(|| malformed("table"))?;
+        let length = end.saturating_sub(start);
+        if fc & 0x4000_0000 != 0 {
+            let offset = (fc & 0x3FFF_FFFF) as usize / 2;
+            let bytes = document
+                .get(offset..offset + length)
+                .ok_or_else(|| malformed("WordDocument"))?;
+            text.push(decode_latin1(bytes))?;
+        } else {
+            let offset = fc as usize;
+            let bytes = document
+                .get(offset..offset + length * 2)
+                .ok_or_else(|| malformed("WordDocument"))?;
+            text.push(decode_utf16(bytes))?;
+        }
+    }
+
+    // Paragraph marks become line breaks, other control characters such as field and cell marks become spaces.
+    Ok(text
+        .join("")
+        .chars()
+        .map(|c| match c {
+            '\r' => '\n',
+            '\n' | '\t' => c,
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect())
+}
+
+/// Reads the records of an Excel workbook stream as pairs of record type and data.
+fn biff_records(stream: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        let kind = u16_at(stream, offset)?;
+        let size = u16_at(stream, offset + 2)? as usize;
+        let data = stream.get(offset + 4..offset + 4 + size)?;
+        offset += 4 + size;
+        Some((kind, data))
+    })
+}
+
+/// Reads the shared string table of an Excel workbook, which may be continued over several records.
+///
+/// When the characters of a string continue in the next record, they start with a new option byte,
+/// so the width of the characters can change in the middle of a string.
+struct SharedStrings<'a> {
+    parts: Vec<&'a [u8]>,
+    part: usize,
+    offset: usize,
+}
+
+impl<'a> SharedStrings<'a> {
+    /// Moves on to the next record once the current one is exhausted.
+    fn advance(&mut self) -> Option<()> {
+        while self.offset >= self.parts.get(self.part)?.len() {
+            self.part += 1;
+            self.offset = 0;
+        }
+        Some(())
+    }
+
+    /// Reads the given number of bytes, which may span several records.
+    fn bytes(&mut self, mut count: usize) -> Option<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(count.min(1024));
+        while count > 0 {
+            self.advance()?;
+            let part = self.parts[self.part];
+            let taken = count.min(part.len() - self.offset);
+            bytes.extend_from_slice(&part[self.offset..self.offset + taken]);
+            self.offset += taken;
+            count -= taken;
+        }
+        Some(bytes)
+    }
+
+    /// Skips the given number of bytes, which may span several records.
+    fn skip(&mut self, count: usize) -> Option<()> {
+        self.bytes(count).map(|_| ())
+    }
+
+    /// Reads the characters of a string, switching their width at record boundaries as announced.
+    fn chars(&mut self, mut count: usize, mut wide: bool) -> Option<String> {
+        let mut text = String::new();
+        loop {
+            let width = if wide { 2 } else { 1 };
+            let available = self.parts.get(self.part)?.len().saturating_sub(self.offset) / width;
+            let taken = count.min(available);
+            let bytes = self.bytes(taken * width)?;
+            text.push_str(&if wide {
+                decode_utf16(&bytes)
+            } else {
+                decode_latin1(&bytes)
+            });
+            count -= taken;
+            if count == 0 {
+                return Some(text);
+            }
+            // The characters continue in the next record, which starts with a new option byte.
+            self.part += 1;
+            self.offset = 1;
+            wide = self.parts.get(self.part)?.first()? & 0x01 != 0;
+        }
+    }
+
+    /// Reads the next string of the table, skipping its formatting runs and phonetic data.
+    fn next_string(&mut self) -> Option<String> {
+        let count = u16::from_le_bytes(self.bytes(2)?.try_into().ok()?) as usize;
+        let options = self.bytes(1)?[0];
+        let runs = if options & 0x08 != 0 {
+            u16::from_le_bytes(self.bytes(2)?.try_into().ok()?) as usize
+        } else {
+            0
+        };
+        let extended = if options & 0x04 != 0 {
+            u32::from_le_bytes(self.bytes(4)?.try_into().ok()?) as usize
+        } else {
+            0
+        };
+        let text = self.chars(count, options & 0x01 != 0)?;
+        self.skip(runs * 4 + extended)?;
+        Some(text)
+    }
+}
+
+/// Reads a string stored inline in a cell record, made up of a 16 bit length, an option byte and the characters.
+fn inline_string(data: &[u8]) -> Option<String> {
+    let count = u16_at(data, 0)? as usize;
+    let options = *data.get(2)?;
+    Some(if options & 0x01 != 0 {
+        decode_utf16(data.get(3..3 + count * 2)?)
+    } else {
+        decode_latin1(data.get(3..3 + count)?)
+    })
+}
+
+/// Attempts to extract the text of an Excel 97-2003 workbook.
+///
+/// Collects the shared strings referenced by the cells, strings stored inline in cells and cached formula results.
+fn excel_text(file: &mut CompoundFile<Cursor<&[u8]>>, limits: &Limits) -> io::Result<String> {
+    let stream = match read_stream(file, "/Workbook") {
+        Ok(stream) => stream,
+        Err(_) => read_stream(file, "/Book")?,
+    };
+    let records: Vec<(u16, &[u8])> = biff_records(&stream).collect();
+    let mut strings = Strings::new(limits);
+    for (i, &(kind, data)) in records.iter().enumerate() {
+        match kind {
+            // FILEPASS
+            0x002F => {
+                return Err(io::Error::other(
+                    "Encrypted Excel workbooks are not supported",
+                ))
+            }
+            // SST, continued by the CONTINUE records following it.
+            0x00FC => {
+                let continued = records[i + 1..]
+                    .iter()
+                    .take_while(|(kind, _)| *kind == 0x003C)
+                    .map(|(_, data)| *data);
+                let mut table = SharedStrings {
+                    parts: std::iter::once(data).chain(continued).collect(),
+                    part: 0,
+                    offset: 8,
+                };
+                let count = u32_at(data, 4).ok_or_else(|| malformed("Workbook"))?;
+                for _ in 0..count {
+                    match table.next_string() {
+                        Some(string) => strings.push(string)?,
+                        None => break,
+                    }
+                }
+            }
+            // LABEL and STRING, with the string following the cell position and format in LABEL.
+            0x0204 => {
+                if let Some(string) = data.get(6..).and_then(inline_string) {
+                    strings.push(string)?;
+                }
+            }
+            0x0207 => {
+                if let Some(string) = inline_string(data) {
+                    strings.push(string)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(strings.join("\n"))
+}
+
+/// Attempts to extract the text of a PowerPoint 97-2003 presentation from its text atoms.
+///
+/// Container records are entered and all atoms holding text, including hyperlink targets, are collected.
+fn powerpoint_text(file: &mut CompoundFile<Cursor<&[u8]>>, limits: &Limits) -> io::Result<String> {
+    let stream = read_stream(file, "/PowerPoint Document")?;
+    let mut strings = Strings::new(limits);
+    let mut offset = 0;
+    while let (Some(options), Some(kind), Some(size)) = (
+        u16_at(&stream, offset),
+        u16_at(&stream, offset + 2),
+        u32_at(&stream, offset + 4),
+    ) {
+        offset += 8;
+        // Containers are marked by a version of 0xF and hold further records.
+        if options & 0x000F == 0x000F {
+            continue;
+        }
+        let end = offset.saturating_add(size as usize).min(stream.len());
+        let data = stream.get(offset..end).unwrap_or_default();
+        match kind {
+            // TextCharsAtom and CString
+            0x0FA0 | 0x0FBA => strings.push(decode_utf16(data))?,
+            // TextBytesAtom
+            0x0FA8 => strings.push(decode_latin1(data))?,
+            _ => {}
+        }
+        offset += size as usize;
+    }
+    Ok(strings.join("\n").replace('\r', "\n"))
+}
+
+impl<'a> ProcessFile<'a> for OleFile<'a> {
+    /// Attempts to parse the given byte slice as a compound file and extracts the text of the document it contains.
+    ///
+    /// The kind of document is recognized by its main stream, so documents written by other applications are supported as well.
+    fn process(&'a self, context: &Context) -> io::Result<Vec<Segment>> {
+        let mut file = CompoundFile::open(Cursor::new(self.0))?;
+        let limits = &context.limits;
+        let text = if file.is_stream("/WordDocument") {
+            word_text(&mut file, limits)?
+        } else if file.is_stream("/Workbook") || file.is_stream("/Book") {
+            excel_text(&mut file, limits)?
+        } else if file.is_stream("/PowerPoint Document") {
+            powerpoint_text(&mut file, limits)?
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Unsupported compound file",
+            ));
+        };
+        Ok(vec![Segment {
+            text,
+            ..Segment::default()
+        }])
+    }
+}
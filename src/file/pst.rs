@@ -0,0 +1,776 @@
+use super::{
+    limit_error,
+    mail::raw_header_segments,
+    msg::{body_segment, push_field, push_recipient, MESSAGE_FIELDS},
+    ole::{decode_latin1, decode_utf16, u16_at, u32_at},
+    Context, Limits, ProcessFile, Segment,
+};
+use crate::emails::Role;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Represents an Outlook data file, either a personal folders file (pst) or an offline folders file (ost),
+/// as a byte slice reference.
+///
+/// Only files with 512-byte pages are supported, so offline folders files of Outlook 2013 and later are rejected.
+pub struct PstFile<'a>(pub(crate) &'a [u8]);
+
+/// Size of the pages of the node and block B-trees.
+const PAGE_SIZE: usize = 512;
+
+/// Maximum level of the B-trees, far more than the files written by Outlook ever need.
+const MAX_LEVEL: u8 = 8;
+
+/// Node ID of the root folder, which is its own parent.
+const ROOT_FOLDER: u32 = 0x122;
+
+/// Node type of folders.
+const FOLDER: u32 = 0x02;
+
+/// Node type of messages.
+const MESSAGE: u32 = 0x04;
+
+/// Node type of attachments.
+const ATTACHMENT: u32 = 0x05;
+
+/// Subnode ID of the recipient table of a message.
+const RECIPIENT_TABLE: u32 = 0x692;
+
+/// Properties that hold message identifiers, which look like addresses but are not.
+const MESSAGE_IDS: [u16; 3] = [0x1035, 0x1039, 0x1042];
+
+/// Properties that are extracted on their own or merely repeat other properties.
+const SPECIAL: [u16; 5] = [0x007D, 0x1000, 0x1013, 0x0E1D, 0x0070];
+
+/// Inverse of the byte permutation applied to data blocks by compressible encryption.
+const DECODE: [u8; 256] = [
+    71, 241, 180, 230, 11, 106, 114, 72, 133, 78, 158, 235, 226, 248, 148, 83, 224, 187, 160, 2,
+    232, 90, 9, 171, 219, 227, 186, 198, 124, 195, 16, 221, 57, 5, 150, 48, 245, 55, 96, 130, 140,
+    201, 19, 74, 107, 29, 243, 251, 143, 38, 151, 202, 145, 23, 1, 196, 50, 45, 110, 49, 149, 255,
+    217, 35, 209, 0, 94, 121, 220, 68, 59, 26, 40, 197, 97, 87, 32, 144, 61, 131, 185, 67, 190,
+    103, 210, 70, 66, 118, 192, 109, 91, 126, 178, 15, 22, 41, 60, 169, 3, 84, 13, 218, 93, 223,
+    246, 183, 199, 98, 205, 141, 6, 211, 105, 92, 134, 214, 20, 247, 165, 102, 117, 172, 177, 233,
+    69, 33, 112, 12, 135, 159, 116, 164, 34, 76, 111, 191, 31, 86, 170, 46, 179, 120, 51, 80, 176,
+    163, 146, 188, 207, 25, 28, 167, 99, 203, 30, 77, 62, 75, 27, 155, 79, 231, 240, 238, 173, 58,
+    181, 89, 4, 234, 64, 85, 37, 81, 229, 122, 137, 56, 104, 82, 123, 252, 39, 174, 215, 189, 250,
+    7, 244, 204, 142, 95, 239, 53, 156, 132, 43, 21, 213, 119, 52, 73, 182, 18, 10, 127, 113, 136,
+    253, 157, 24, 65, 125, 147, 216, 88, 44, 206, 254, 36, 175, 222, 184, 54, 200, 161, 128, 166,
+    153, 152, 168, 47, 14, 129, 101, 115, 228, 194, 162, 138, 212, 225, 17, 208, 8, 139, 42, 242,
+    237, 154, 100, 63, 193, 108, 249, 236,
+];
+
+/// Creates the error reported for malformed structures of an Outlook data file.
+fn malformed(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Malformed {}", what))
+}
+
+/// Checks whether the given bytes start with the header of an Outlook data file.
+pub(crate) fn is_pst(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"!BDN") && matches!(bytes.get(8..10), Some(b"SM" | b"SO"))
+}
+
+/// Reads a little endian `u64` at the given offset, if in bounds.
+fn u64_at(bytes: &[u8], offset: usize) -> Option<u64> {
+    let bytes = bytes.get(offset..offset.checked_add(8)?)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Locates the data and the subnodes of a node, both given as block IDs.
+#[derive(Clone, Copy)]
+struct NodeEntry {
+    data: u64,
+    subnodes: u64,
+}
+
+/// Describes a node of the node B-tree.
+struct TopNode {
+    nid: u32,
+    parent: u32,
+    entry: NodeEntry,
+}
+
+/// Holds the value of a property, whose interpretation depends on its type.
+struct Property {
+    kind: u16,
+    value: Vec<u8>,
+}
+
+/// Decodes a string property, returning `None` for other types and empty strings.
+fn text(property: &Property) -> Option<String> {
+    let text = match property.kind {
+        0x001F => decode_utf16(&property.value),
+        0x001E => decode_latin1(&property.value),
+        _ => return None,
+    };
+    let text = text.trim_end_matches('\0').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Decodes the string property with the given ID, if present.
+fn string(properties: &BTreeMap<u16, Property>, id: u16) -> Option<String> {
+    properties.get(&id).and_then(text)
+}
+
+/// Reads the blocks of an Outlook data file, which is stored in either the ANSI or the Unicode format.
+///
+/// Both formats share the same structures, but the Unicode format uses 64-bit IDs and offsets instead of 32-bit ones.
+struct Store<'a> {
+    bytes: &'a [u8],
+    unicode: bool,
+    /// Data blocks are obfuscated with compressible encryption.
+    encrypted: bool,
+    /// Offset and size of each block by its ID.
+    blocks: HashMap<u64, (u64, u16)>,
+    /// Limits on the data of a single node and on the data read from the file in total.
+    limits: Limits,
+    /// Number of bytes extracted so far from the current input file, shared with the context.
+    extracted: Arc<AtomicU64>,
+}
+
+impl<'a> Store<'a> {
+    /// Attempts to read the header and both B-trees of an Outlook data file and returns the nodes it contains.
+    fn open(bytes: &'a [u8], context: &Context) -> io::Result<(Store<'a>, Vec<TopNode>)> {
+        let unicode = match u16_at(bytes, 10) {
+            Some(14 | 15) => false,
+            Some(23) => true,
+            Some(36) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Outlook data files with 4 KiB pages, as written by Outlook 2013 and later, are not supported",
+                ))
+            }
+            _ => return Err(malformed("Outlook data file header")),
+        };
+        let (nbt, bbt, crypt) = if unicode {
+            (u64_at(bytes, 224), u64_at(bytes, 240), bytes.get(513))
+        } else {
+            (
+                u32_at(bytes, 188).map(u64::from),
+                u32_at(bytes, 196).map(u64::from),
+                bytes.get(461),
+            )
+        };
+        let (Some(nbt), Some(bbt), Some(&crypt)) = (nbt, bbt, crypt) else {
+            return Err(malformed("Outlook data file header"));
+        };
+        let encrypted = match crypt {
+            0 => false,
+            1 => true,
+            2 => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Outlook data files with high encryption are not supported",
+                ))
+            }
+            _ => return Err(malformed("Outlook data file header")),
+        };
+        let mut store = Store {
+            bytes,
+            unicode,
+            encrypted,
+            blocks: HashMap::new(),
+            limits: context.limits,
+            extracted: Arc::clone(&context.extracted),
+        };
+
+        let width = store.width();
+        let mut blocks = HashMap::new();
+        store.btree(bbt, 0x80, None, &mut HashSet::new(), &mut |entry| {
+            let (Some(bid), Some(offset), Some(size)) = (
+                store.id_at(entry, 0),
+                store.id_at(entry, width),
+                u16_at(entry, 2 * width),
+            ) else {
+                return Err(malformed("block B-tree entry"));
+            };
+            blocks.insert(bid & !1, (offset, size));
+            Ok(())
+        })?;
+        store.blocks = blocks;
+
+        let mut nodes = Vec::new();
+        store.btree(nbt, 0x81, None, &mut HashSet::new(), &mut |entry| {
+            let (Some(nid), Some(data), Some(subnodes), Some(parent)) = (
+                u32_at(entry, 0),
+                store.id_at(entry, width),
+                store.id_at(entry, 2 * width),
+                u32_at(entry, 3 * width),
+            ) else {
+                return Err(malformed("node B-tree entry"));
+            };
+            nodes.push(TopNode {
+                nid,
+                parent,
+                entry: NodeEntry { data, subnodes },
+            });
+            Ok(())
+        })?;
+        Ok((store, nodes))
+    }
+
+    /// Returns the size of IDs and offsets in bytes.
+    fn width(&self) -> usize {
+        if self.unicode {
+            8
+        } else {
+            4
+        }
+    }
+
+    /// Reads an ID or offset at the given offset, if in bounds.
+    fn id_at(&self, bytes: &[u8], offset: usize) -> Option<u64> {
+        if self.unicode {
+            u64_at(bytes, offset)
+        } else {
+            u32_at(bytes, offset).map(u64::from)
+        }
+    }
+
+    /// Attempts to walk the B-tree page at the given offset and passes each leaf entry to `f`.
+    ///
+    /// The level of every page has to be one less than the level of its parent, and a page referenced twice
+    /// is rejected as malformed.
+    fn btree(
+        &self,
+        offset: u64,
+        kind: u8,
+        level: Option<u8>,
+        visited: &mut HashSet<u64>,
+        f: &mut dyn FnMut(&[u8]) -> io::Result<()>,
+    ) -> io::Result<()> {
+        if !visited.insert(offset) {
+            return Err(malformed("B-tree page reference"));
+        }
+        let page = usize::try_from(offset)
+            .ok()
+            .and_then(|offset| self.bytes.get(offset..offset.checked_add(PAGE_SIZE)?))
+            .ok_or_else(|| malformed("B-tree page reference"))?;
+        // Entries are followed by their count, size and level, and a trailer starting with the page type.
+        let (meta, trailer) = if self.unicode { (488, 496) } else { (496, 500) };
+        let count = page[meta] as usize;
+        let size = page[meta + 2] as usize;
+        let page_level = page[meta + 3];
+        if page[trailer] != kind
+            || page_level > MAX_LEVEL
+            || level.is_some_and(|level| level != page_level)
+            || count * size > meta
+        {
+            return Err(malformed("B-tree page"));
+        }
+        for entry in page[..count * size].chunks_exact(size.max(1)) {
+            if page_level == 0 {
+                f(entry)?;
+            } else {
+                let child = self
+                    .id_at(entry, 2 * self.width())
+                    .ok_or_else(|| malformed("B-tree page"))?;
+                self.btree(child, kind, Some(page_level - 1), visited, f)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Attempts to look up the raw bytes of the block with the given ID.
+    fn block(&self, bid: u64) -> io::Result<&'a [u8]> {
+        let &(offset, size) = self
+            .blocks
+            .get(&(bid & !1))
+            .ok_or_else(|| malformed("block reference"))?;
+        usize::try_from(offset)
+            .ok()
+            .and_then(|offset| self.bytes.get(offset..offset.checked_add(size as usize)?))
+            .ok_or_else(|| malformed("block reference"))
+    }
+
+    /// Attempts to read the data blocks of a node, following the tree of internal blocks that lists them.
+    ///
+    /// The `level` of an internal block is checked against its parent, with 0 standing for a data block.
+    /// The size of the data blocks is added to `total` for the node, and to the bytes extracted from the input file.
+    fn data(
+        &self,
+        bid: u64,
+        level: Option<u8>,
+        blocks: &mut Vec<Vec<u8>>,
+        visited: &mut HashSet<u64>,
+        total: &mut u64,
+    ) -> io::Result<()> {
+        if !visited.insert(bid & !1) {
+            return Err(malformed("data tree"));
+        }
+        let block = self.block(bid)?;
+        // Internal blocks are marked by the second bit of their ID.
+        if bid & 2 == 0 {
+            if level.is_some_and(|level| level != 0) {
+                return Err(malformed("data tree"));
+            }
+            let size = block.len() as u64;
+            *total += size;
+            let extracted = self.extracted.fetch_add(size, Ordering::Relaxed) + size;
+            let total_exceeded = extracted > self.limits.max_total_size;
+            if total_exceeded || *total > self.limits.max_member_size {
+                return Err(limit_error(&self.limits, total_exceeded, *total));
+            }
+            let mut block = block.to_vec();
+            if self.encrypted {
+                for byte in &mut block {
+                    *byte = DECODE[*byte as usize];
+                }
+            }
+            blocks.push(block);
+            return Ok(());
+        }
+        let (Some(&1), Some(&block_level), Some(count)) =
+            (block.first(), block.get(1), u16_at(block, 2))
+        else {
+            return Err(malformed("data tree"));
+        };
+        if !matches!(block_level, 1 | 2) || level.is_some_and(|level| level != block_level) {
+            return Err(malformed("data tree"));
+        }
+        for i in 0..count as usize {
+            let child = self
+                .id_at(block, 8 + i * self.width())
+                .ok_or_else(|| malformed("data tree"))?;
+            self.data(child, Some(block_level - 1), blocks, visited, total)?;
+        }
+        Ok(())
+    }
+
+    /// Attempts to read the tree of subnodes of a node into `subnodes`.
+    ///
+    /// The tree has at most two levels, an intermediate block listing leaf blocks, which list the subnodes.
+    fn subnodes(
+        &self,
+        bid: u64,
+        level: Option<u8>,
+        visited: &mut HashSet<u64>,
+        subnodes: &mut HashMap<u32, NodeEntry>,
+    ) -> io::Result<()> {
+        if bid == 0 {
+            return Ok(());
+        }
+        if !visited.insert(bid & !1) {
+            return Err(malformed("subnode tree"));
+        }
+        let block = self.block(bid)?;
+        let (Some(&2), Some(&block_level), Some(count)) =
+            (block.first(), block.get(1), u16_at(block, 2))
+        else {
+            return Err(malformed("subnode tree"));
+        };
+        if block_level > 1 || level.is_some_and(|level| level != block_level) {
+            return Err(malformed("subnode tree"));
+        }
+        let width = self.width();
+        let start = if self.unicode { 8 } else { 4 };
+        let size = if block_level == 0 {
+            3 * width
+        } else {
+            2 * width
+        };
+        for i in 0..count as usize {
+            let entry = block
+                .get(start + i * size..start + (i + 1) * size)
+                .ok_or_else(|| malformed("subnode tree"))?;
+            let nid = u32_at(entry, 0).unwrap_or_default();
+            let first = self.id_at(entry, width).unwrap_or_default();
+            if block_level == 0 {
+                let subnodes_bid = self.id_at(entry, 2 * width).unwrap_or_default();
+                subnodes.insert(
+                    nid,
+                    NodeEntry {
+                        data: first,
+                        subnodes: subnodes_bid,
+                    },
+                );
+            } else {
+                self.subnodes(first, Some(0), visited, subnodes)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Attempts to read the data blocks and the subnodes of a node.
+    fn node(&self, entry: NodeEntry) -> io::Result<Node> {
+        let mut blocks = Vec::new();
+        if entry.data != 0 {
+            self.data(entry.data, None, &mut blocks, &mut HashSet::new(), &mut 0)?;
+        }
+        let mut subnodes = HashMap::new();
+        self.subnodes(entry.subnodes, None, &mut HashSet::new(), &mut subnodes)?;
+        Ok(Node { blocks, subnodes })
+    }
+}
+
+/// Holds the data of a node, which is a heap spread over its blocks, and the subnodes it refers to.
+struct Node {
+    blocks: Vec<Vec<u8>>,
+    subnodes: HashMap<u32, NodeEntry>,
+}
+
+impl Node {
+    /// Looks up the heap allocation with the given heap ID.
+    ///
+    /// A heap ID consists of a 5-bit type, which is 0, an 11-bit allocation index starting at 1 and a 16-bit block index.
+    fn heap(&self, hid: u32) -> Option<&[u8]> {
+        let index = (hid >> 5 & 0x07FF) as usize;
+        let block = self.blocks.get((hid >> 16) as usize)?;
+        let map = u16_at(block, 0)? as usize;
+        if index == 0 || index > u16_at(block, map)? as usize {
+            return None;
+        }
+        let start = u16_at(block, map + 2 + 2 * index)? as usize;
+        let end = u16_at(block, map + 4 + 2 * index)? as usize;
+        block.get(start..end)
+    }
+
+    /// Attempts to read the value referred to by a heap ID, or by the ID of a subnode if its type bits are set.
+    fn value(&self, store: &Store, hnid: u32) -> io::Result<Vec<u8>> {
+        if hnid == 0 {
+            Ok(Vec::new())
+        } else if hnid & 0x1F == 0 {
+            self.heap(hnid)
+                .map(<[u8]>::to_vec)
+                .ok_or_else(|| malformed("heap reference"))
+        } else {
+            let entry = self
+                .subnodes
+                .get(&hnid)
+                .ok_or_else(|| malformed("subnode reference"))?;
+            Ok(store.node(*entry)?.blocks.concat())
+        }
+    }
+
+    /// Attempts to check the signature of the heap and returns the heap ID of its client structure.
+    fn root(&self, client: u8, what: &str) -> io::Result<u32> {
+        match self.blocks.first() {
+            Some(block) if block.get(2) == Some(&0xEC) && block.get(3) == Some(&client) => {
+                u32_at(block, 4).ok_or_else(|| malformed(what))
+            }
+            _ => Err(malformed(what)),
+        }
+    }
+
+    /// Attempts to read the leaf records of the B-tree on the heap with the given header.
+    fn records(&self, hid: u32) -> io::Result<Vec<&[u8]>> {
+        let header = self
+            .heap(hid)
+            .filter(|header| header.len() >= 8 && header[0] == 0xB5)
+            .ok_or_else(|| malformed("heap B-tree"))?;
+        let key = header[1] as usize;
+        let size = header[2] as usize;
+        if header[3] > MAX_LEVEL {
+            return Err(malformed("heap B-tree"));
+        }
+        let mut records = Vec::new();
+        self.collect_records(
+            u32_at(header, 4).unwrap_or_default(),
+            header[3],
+            key,
+            size,
+            &mut HashSet::new(),
+            &mut records,
+        )?;
+        Ok(records)
+    }
+
+    /// Attempts to collect the leaf records below the heap B-tree node with the given heap ID.
+    ///
+    /// Records of intermediate nodes hold a key followed by the heap ID of the child node, down to the `level` 0.
+    fn collect_records<'n>(
+        &'n self,
+        hid: u32,
+        level: u8,
+        key: usize,
+        size: usize,
+        visited: &mut HashSet<u32>,
+        records: &mut Vec<&'n [u8]>,
+    ) -> io::Result<()> {
+        if hid == 0 {
+            return Ok(());
+        }
+        if !visited.insert(hid) {
+            return Err(malformed("heap B-tree"));
+        }
+        let data = self.heap(hid).ok_or_else(|| malformed("heap B-tree"))?;
+        if level == 0 {
+            records.extend(data.chunks_exact((key + size).max(1)));
+        } else {
+            for record in data.chunks_exact(key + 4) {
+                let next = u32_at(record, key).unwrap_or_default();
+                self.collect_records(next, level - 1, key, size, visited, records)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Attempts to read the properties of a property context, such as a folder, message or attachment.
+    ///
+    /// Values of up to 4 bytes are stored in place, all others on the heap or in a subnode.
+    fn properties(&self, store: &Store) -> io::Result<BTreeMap<u16, Property>> {
+        let root = self.root(0xBC, "property context")?;
+        let mut properties = BTreeMap::new();
+        for record in self.records(root)? {
+            let (Some(id), Some(kind), Some(hnid)) =
+                (u16_at(record, 0), u16_at(record, 2), u32_at(record, 4))
+            else {
+                continue;
+            };
+            let value = match kind {
+                0x0002 | 0x0003 | 0x0004 | 0x000A | 0x000B => hnid.to_le_bytes().to_vec(),
+                _ => self.value(store, hnid)?,
+            };
+            properties.insert(id, Property { kind, value });
+        }
+        Ok(properties)
+    }
+
+    /// Attempts to read the rows of a table context, such as the recipient table of a message.
+    ///
+    /// Each row is a fixed size record of cells, and a bitmap at the end tells which cells hold a value.
+    /// Cells of variable size hold a heap ID or a subnode ID instead of their value.
+    fn rows(&self, store: &Store) -> io::Result<Vec<BTreeMap<u16, Property>>> {
+        let root = self.root(0x7C, "table context")?;
+        let info = self
+            .heap(root)
+            .filter(|info| info.len() >= 22 && info[0] == 0x7C)
+            .ok_or_else(|| malformed("table context"))?;
+        let columns: Vec<&[u8]> = info[22..].chunks_exact(8).take(info[1] as usize).collect();
+        let bitmap = u16_at(info, 6).unwrap_or_default() as usize;
+        let size = u16_at(info, 8).unwrap_or_default() as usize;
+        let count = self.records(u32_at(info, 10).unwrap_or_default())?.len();
+        let hnid = u32_at(info, 14).unwrap_or_default();
+        if size == 0 || hnid == 0 {
+            return Ok(Vec::new());
+        }
+
+        // Rows never span blocks, so each block of a subnode holds a whole number of rows followed by padding.
+        let blocks = if hnid & 0x1F == 0 {
+            vec![self.value(store, hnid)?]
+        } else {
+            let entry = self
+                .subnodes
+                .get(&hnid)
+                .ok_or_else(|| malformed("subnode reference"))?;
+            store.node(*entry)?.blocks
+        };
+        let mut rows = Vec::new();
+        for row in blocks
+            .iter()
+            .flat_map(|block| block.chunks_exact(size))
+            .take(count)
+        {
+            let mut properties = BTreeMap::new();
+            for column in &columns {
+                let tag = u32_at(column, 0).unwrap_or_default();
+                let offset = u16_at(column, 4).unwrap_or_default() as usize;
+                let bit = column[7] as usize;
+                let present = row
+                    .get(bitmap + bit / 8)
+                    .is_some_and(|byte| byte & (0x80 >> (bit % 8)) != 0);
+                let Some(cell) = row
+                    .get(offset..offset + column[6] as usize)
+                    .filter(|_| present)
+                else {
+                    continue;
+                };
+                let kind = tag as u16;
+                let variable = matches!(kind, 0x000D | 0x001E | 0x001F | 0x0048 | 0x0102)
+                    || kind & 0x1000 != 0;
+                let value = match (variable, u32_at(cell, 0)) {
+                    (true, Some(hnid)) => self.value(store, hnid)?,
+                    _ => cell.to_vec(),
+                };
+                properties.insert((tag >> 16) as u16, Property { kind, value });
+            }
+            rows.push(properties);
+        }
+        Ok(rows)
+    }
+}
+
+/// Attempts to extract the text of a message, its recipients and everything attached to it.
+///
+/// The sender, the recipients and the subject are extracted as header fields tagged with the role of their addresses,
+/// followed by all other string properties, which hold addresses of contacts and meetings as well.
+fn message_segments(store: &Store, node: &Node, context: &Context) -> io::Result<Vec<Segment>> {
+    let properties = node.properties(store)?;
+    let mut segments = Vec::new();
+    let mut offset = 0;
+    for (id, name, role) in MESSAGE_FIELDS {
+        if let Some(value) = string(&properties, id) {
+            push_field(&mut segments, &mut offset, name, &value, role);
+        }
+    }
+
+    if let Some(entry) = node.subnodes.get(&RECIPIENT_TABLE) {
+        for recipient in store.node(*entry)?.rows(store)? {
+            let kind = recipient
+                .get(&0x0C15)
+                .and_then(|property| u32_at(&property.value, 0));
+            push_recipient(&mut segments, &mut offset, kind, |id| {
+                string(&recipient, id)
+            });
+        }
+    }
+
+    for (&id, property) in &properties {
+        if MESSAGE_FIELDS.iter().any(|&(field, _, _)| field == id)
+            || MESSAGE_IDS.contains(&id)
+            || SPECIAL.contains(&id)
+        {
+            continue;
+        }
+        if let Some(value) = text(property) {
+            let name = format!("Property-{:04X}", id);
+            push_field(&mut segments, &mut offset, &name, &value, Role::Header);
+        }
+    }
+
+    if let Some(headers) = string(&properties, 0x007D) {
+        segments.extend(raw_header_segments(&headers, offset));
+    }
+
+    segments.extend(body_segment(string(&properties, 0x1000), || {
+        let html = properties.get(&0x1013)?;
+        text(html).or_else(|| Some(String::from_utf8_lossy(&html.value).into_owned()))
+    }));
+
+    let mut attachments: Vec<(u32, NodeEntry)> = node
+        .subnodes
+        .iter()
+        .filter(|(&nid, _)| nid & 0x1F == ATTACHMENT)
+        .map(|(&nid, &entry)| (nid, entry))
+        .collect();
+    attachments.sort_by_key(|&(nid, _)| nid);
+    for (i, (_, entry)) in attachments.into_iter().enumerate() {
+        let number = format!("attachment {}", i + 1);
+        match store
+            .node(entry)
+            .and_then(|attachment| attachment_segments(store, &attachment, i, &number, context))
+        {
+            Ok(found) => segments.extend(found),
+            Err(e) => context.skip_member(&number, e)?,
+        }
+    }
+    Ok(segments)
+}
+
+/// Attempts to extract the text of an attachment at the given position, named by its file name, or by its number.
+///
+/// Attached files are fed back through the file type detection, embedded messages are processed like the message itself.
+fn attachment_segments(
+    store: &Store,
+    attachment: &Node,
+    ordinal: usize,
+    number: &str,
+    context: &Context,
+) -> io::Result<Vec<Segment>> {
+    let properties = attachment.properties(store)?;
+    let name = [0x3707, 0x3704, 0x3001]
+        .into_iter()
+        .find_map(|id| string(&properties, id))
+        .unwrap_or_else(|| number.to_string());
+    match properties.get(&0x3701) {
+        Some(Property {
+            kind: 0x0102,
+            value,
+        }) => {
+            let bytes = context.read_member(value.as_slice(), None)?;
+            context.process_member(ordinal, &name, &bytes)
+        }
+        Some(Property {
+            kind: 0x000D,
+            value,
+        }) => {
+            let entry = u32_at(value, 0)
+                .and_then(|nid| attachment.subnodes.get(&nid))
+                .ok_or_else(|| malformed("embedded message"))?;
+            let embedded = store.node(*entry)?;
+            let mut segments = message_segments(store, &embedded, &context.within(&name).enter()?)?;
+            for segment in &mut segments {
+                segment.location.nest(ordinal, &name);
+            }
+            Ok(segments)
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Returns the path of a folder made of the names of its ancestors, without the root folder.
+fn folder_path(folders: &HashMap<u32, (u32, String)>, mut nid: u32) -> String {
+    let mut names = Vec::new();
+    // Every folder is visited at most once, even if the parents of a hostile file form a loop.
+    while nid != ROOT_FOLDER && names.len() < folders.len() {
+        let Some((parent, name)) = folders.get(&nid) else {
+            break;
+        };
+        names.push(name.as_str());
+        nid = *parent;
+    }
+    names.reverse();
+    names.join("/")
+}
+
+impl<'a> ProcessFile<'a> for PstFile<'a> {
+    /// Attempts to parse the given byte slice as an Outlook data file and processes each message of each folder.
+    ///
+    /// Every message is named by its folder path and its number within the folder, starting at 1,
+    /// e.g. `Top of Outlook data file/Inbox/message 3`.
+    /// Messages that fail to process are reported and skipped like archive members.
+    fn process(&'a self, context: &Context) -> io::Result<Vec<Segment>> {
+        let context = context.enter()?;
+        let (store, nodes) = Store::open(self.0, &context)?;
+
+        let folders: HashMap<u32, (u32, String)> = nodes
+            .iter()
+            .filter(|node| node.nid & 0x1F == FOLDER)
+            .map(|node| {
+                let name = store
+                    .node(node.entry)
+                    .and_then(|folder| folder.properties(&store))
+                    .ok()
+                    .and_then(|properties| string(&properties, 0x3001))
+                    .unwrap_or_else(|| format!("folder {}", node.nid));
+                (node.nid, (node.parent, name))
+            })
+            .collect();
+        let mut messages: Vec<(String, u32, NodeEntry)> = nodes
+            .iter()
+            .filter(|node| node.nid & 0x1F == MESSAGE)
+            .map(|node| (folder_path(&folders, node.parent), node.nid, node.entry))
+            .collect();
+        context.check_members(messages.len())?;
+        messages.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+
+        let mut segments = Vec::new();
+        let mut number = 0;
+        for (i, (folder, _, entry)) in messages.iter().enumerate() {
+            number = match i.checked_sub(1).map(|previous| &messages[previous].0) {
+                Some(previous) if previous == folder => number + 1,
+                _ => 1,
+            };
+            let name = match folder.as_str() {
+                "" => format!("message {}", number),
+                folder => format!("{}/message {}", folder, number),
+            };
+            match store
+                .node(*entry)
+                .and_then(|message| message_segments(&store, &message, &context.within(&name)))
+            {
+                Ok(found) => segments.extend(found.into_iter().map(|mut segment| {
+                    segment.location.nest(i, &name);
+                    segment
+                })),
+                Err(e) => context.skip_member(&name, e)?,
+            }
+        }
+        Ok(segments)
+    }
+}
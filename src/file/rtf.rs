@@ -0,0 +1,337 @@
+use super::{epub::mailto_address, Context, ProcessFile, Segment};
+use encoding_rs::Encoding;
+use std::io;
+
+/// Represents a document in Rich Text Format (rtf) as a byte slice reference.
+pub struct RtfFile<'a>(pub(crate) &'a [u8]);
+
+/// Destinations that hold formatting tables or binary data instead of text.
+const SKIPPED: [&str; 14] = [
+    "colortbl",
+    "datastore",
+    "filetbl",
+    "fonttbl",
+    "latentstyles",
+    "listoverridetable",
+    "listtable",
+    "objdata",
+    "pict",
+    "revtbl",
+    "rsidtbl",
+    "stylesheet",
+    "themedata",
+    "xmlnstbl",
+];
+
+/// Returns the encoding of the given Windows code page, defaulting to Windows-1252.
+fn code_page(number: i32) -> &'static Encoding {
+    let label = match number {
+        874 | 1250..=1258 => format!("windows-{}", number),
+        866 => "ibm866".to_string(),
+        932 => "shift_jis".to_string(),
+        936 => "gbk".to_string(),
+        949 => "euc-kr".to_string(),
+        950 => "big5".to_string(),
+        10000 => "macintosh".to_string(),
+        20866 => "koi8-r".to_string(),
+        21866 => "koi8-u".to_string(),
+        28591..=28606 => format!("iso-8859-{}", number - 28590),
+        65001 => "utf-8".to_string(),
+        _ => return encoding_rs::WINDOWS_1252,
+    };
+    Encoding::for_label(label.as_bytes()).unwrap_or(encoding_rs::WINDOWS_1252)
+}
+
+/// Returns the address a `HYPERLINK "mailto:..."` field instruction points to, if any.
+fn mailto_target(instruction: &str) -> Option<String> {
+    let rest = instruction.trim_start().strip_prefix("HYPERLINK")?;
+    mailto_address(rest.split('"').nth(1)?)
+}
+
+/// State of a group, which is inherited by its nested groups.
+#[derive(Clone, Copy)]
+struct Group {
+    /// The group is a destination without text, so everything within it is dropped.
+    skip: bool,
+    /// Number of fallback characters that follow a `\u` character.
+    fallback: usize,
+}
+
+/// Converts the control words, groups and escapes of an RTF document into plain text.
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    groups: Vec<Group>,
+    encoding: &'static Encoding,
+    /// Bytes of the document code page, which are decoded together as they may form multi-byte characters.
+    pending: Vec<u8>,
+    /// Number of fallback characters still to be dropped after a `\u` character.
+    skipping: usize,
+    /// High surrogate of a `\u` character, which is combined with the low surrogate of the next one.
+    high_surrogate: Option<u16>,
+    /// Nesting depth of the field instruction being collected, if any.
+    instruction_depth: Option<usize>,
+    instruction: String,
+    text: String,
+}
+
+impl<'a> Parser<'a> {
+    fn new(bytes: &'a [u8]) -> Parser<'a> {
+        Parser {
+            bytes,
+            position: 0,
+            groups: vec![Group {
+                skip: false,
+                fallback: 1,
+            }],
+            encoding: encoding_rs::WINDOWS_1252,
+            pending: Vec::new(),
+            skipping: 0,
+            high_surrogate: None,
+            instruction_depth: None,
+            instruction: String::new(),
+            text: String::new(),
+        }
+    }
+
+    fn group(&mut self) -> &mut Group {
+        // The outermost group is never popped, so there always is a current group.
+        self.groups.last_mut().unwrap()
+    }
+
+    /// Appends text to the field instruction being collected or to the document text, unless its group is skipped.
+    fn emit(&mut self, text: &str) {
+        // A high surrogate that is not followed by a low surrogate does not form a character.
+        let unpaired = self
+            .high_surrogate
+            .take()
+            .map(|_| char::REPLACEMENT_CHARACTER);
+        if self.group().skip {
+            return;
+        }
+        let target = match self.instruction_depth {
+            Some(_) => &mut self.instruction,
+            None => &mut self.text,
+        };
+        target.extend(unpaired);
+        target.push_str(text);
+    }
+
+    /// Queues a byte of the document code page, unless it is a fallback character.
+    fn push_byte(&mut self, byte: u8) {
+        if self.skipping > 0 {
+            self.skipping -= 1;
+        } else {
+            self.pending.push(byte);
+        }
+    }
+
+    /// Decodes the queued bytes of the document code page.
+    fn flush(&mut self) {
+        if !self.pending.is_empty() {
+            let pending = std::mem::take(&mut self.pending);
+            let (text, _, _) = self.encoding.decode(&pending);
+            self.emit(&text);
+        }
+    }
+
+    /// Reads a control word with its optional numeric parameter, consuming the space that may delimit it.
+    fn control_word(&mut self) -> (&'a str, Option<i32>) {
+        let bytes = self.bytes;
+        let start = self.position;
+        while self.position < bytes.len() && bytes[self.position].is_ascii_alphabetic() {
+            self.position += 1;
+        }
+        let word = std::str::from_utf8(&bytes[start..self.position]).unwrap_or_default();
+        let number_start = self.position;
+        if bytes.get(self.position) == Some(&b'-') {
+            self.position += 1;
+        }
+        while self.position < bytes.len() && bytes[self.position].is_ascii_digit() {
+            self.position += 1;
+        }
+        let number = std::str::from_utf8(&bytes[number_start..self.position])
+            .ok()
+            .and_then(|number| number.parse::<i64>().ok())
+            .map(|number| number.clamp(i32::MIN as i64, i32::MAX as i64) as i32);
+        if bytes.get(self.position) == Some(&b' ') {
+            self.position += 1;
+        }
+        (word, number)
+    }
+
+    /// Handles a control word, which may produce text, change the state of the group or start a destination.
+    fn handle_word(&mut self, word: &str, number: Option<i32>, ignorable: bool) {
+        if self.skipping > 0 {
+            self.skipping -= 1;
+            return;
+        }
+        let text = match word {
+            "par" | "line" | "sect" | "page" | "row" => "\n",
+            "tab" | "cell" => "\t",
+            "emspace" | "enspace" | "qmspace" => " ",
+            "emdash" => "\u{2014}",
+            "endash" => "\u{2013}",
+            "bullet" => "\u{2022}",
+            "lquote" => "\u{2018}",
+            "rquote" => "\u{2019}",
+            "ldblquote" => "\u{201C}",
+            "rdblquote" => "\u{201D}",
+            "u" => {
+                // Negative values stand for the upper half of the 16-bit range.
+                let unit = number.unwrap_or_default().rem_euclid(0x10000) as u16;
+                let high = self.high_surrogate.take();
+                if (0xD800..0xDC00).contains(&unit) {
+                    if high.is_some() {
+                        self.emit("\u{FFFD}");
+                    }
+                    self.high_surrogate = Some(unit);
+                } else {
+                    let text: String = char::decode_utf16(high.into_iter().chain([unit]))
+                        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                        .collect();
+                    self.emit(&text);
+                }
+                self.skipping = self.group().fallback;
+                return;
+            }
+            "uc" => {
+                self.group().fallback = number.unwrap_or(1).max(0) as usize;
+                return;
+            }
+            "ansicpg" => {
+                self.encoding = code_page(number.unwrap_or_default());
+                return;
+            }
+            "bin" => {
+                // Binary data is dropped without interpreting it.
+                let length = number.unwrap_or_default().max(0) as usize;
+                self.position = self.position.saturating_add(length).min(self.bytes.len());
+                return;
+            }
+            "fldinst" => {
+                if self.instruction_depth.is_none() {
+                    self.instruction_depth = Some(self.groups.len());
+                    self.instruction.clear();
+                }
+                return;
+            }
+            word if ignorable || SKIPPED.contains(&word) => {
+                self.group().skip = true;
+                return;
+            }
+            _ => return,
+        };
+        self.emit(text);
+    }
+
+    /// Ends the current group, emitting the target of a `mailto:` hyperlink once its field instruction is complete.
+    fn end_group(&mut self) {
+        if self.groups.len() > 1 {
+            self.groups.pop();
+        }
+        self.skipping = 0;
+        if self
+            .instruction_depth
+            .is_some_and(|depth| self.groups.len() < depth)
+        {
+            self.instruction_depth = None;
+            if let Some(address) = mailto_target(&self.instruction) {
+                self.emit(&format!(" {} ", address));
+            }
+        }
+    }
+
+    /// Parses the whole document and returns its text.
+    fn parse(mut self) -> String {
+        let mut ignorable = false;
+        while let Some(&byte) = self.bytes.get(self.position) {
+            self.position += 1;
+            match byte {
+                b'{' => {
+                    self.flush();
+                    let group = *self.group();
+                    self.groups.push(group);
+                }
+                b'}' => {
+                    self.flush();
+                    self.end_group();
+                }
+                b'\\' => match self.bytes.get(self.position).copied() {
+                    Some(b'\'') => {
+                        let hex = self.bytes.get(self.position + 1..self.position + 3);
+                        self.position = (self.position + 3).min(self.bytes.len());
+                        if let Some(byte) = hex
+                            .and_then(|hex| std::str::from_utf8(hex).ok())
+                            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                        {
+                            self.push_byte(byte);
+                        }
+                    }
+                    Some(c) if c.is_ascii_alphabetic() => {
+                        self.flush();
+                        let (word, number) = self.control_word();
+                        self.handle_word(word, number, ignorable);
+                        ignorable = false;
+                        continue;
+                    }
+                    Some(b'*') => {
+                        self.position += 1;
+                        ignorable = true;
+                        continue;
+                    }
+                    Some(symbol) => {
+                        self.position += 1;
+                        self.flush();
+                        let text = match symbol {
+                            b'\\' | b'{' | b'}' => {
+                                self.push_byte(symbol);
+                                continue;
+                            }
+                            b'~' => " ",
+                            b'_' => "-",
+                            b'\r' | b'\n' => "\n",
+                            _ => "",
+                        };
+                        if self.skipping > 0 {
+                            self.skipping -= 1;
+                        } else {
+                            self.emit(text);
+                        }
+                    }
+                    None => {}
+                },
+                // Line breaks in the source only wrap the markup.
+                b'\r' | b'\n' => {}
+                byte => self.push_byte(byte),
+            }
+            ignorable = false;
+        }
+        self.flush();
+        self.text
+    }
+}
+
+impl<'a> ProcessFile<'a> for RtfFile<'a> {
+    /// Attempts to convert the given byte slice from Rich Text Format into plain text.
+    ///
+    /// Escaped characters are decoded using the code page of the document, and formatting tables and pictures are dropped.
+    /// The addresses of `mailto:` hyperlinks are taken from their field instructions and added to the text.
+    fn process(&'a self, _context: &Context) -> io::Result<Vec<Segment>> {
+        Ok(vec![Segment {
+            text: Parser::new(self.0).parse(),
+            ..Segment::default()
+        }])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combines_surrogate_pairs() {
+        let text = Parser::new(br"{\rtf1\uc1 \u-10179?\u-8704? \u55357?x\u56832?}").parse();
+        assert_eq!(text, "\u{1F600} \u{FFFD}x\u{FFFD}");
+    }
+}
@@ -0,0 +1,109 @@
+use super::{is_rejected, locked, Context, ProcessFile, Segment};
+use sevenz_rust::{Archive, Error, Password, SevenZReader};
+use std::{
+    io::{self, Cursor},
+    sync::atomic::Ordering,
+};
+
+/// Represents a 7z archive as a byte slice reference.
+pub struct SevenZipFile<'a>(pub(crate) &'a [u8]);
+
+/// Converts an error of the 7z decoder into an `io::Error`.
+fn to_io_error(err: Error) -> io::Error {
+    match err {
+        Error::Io(err, _) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)),
+    }
+}
+
+/// Lists the packed size of the folder holding each file, in the order the decoder visits the files of folders.
+///
+/// Files of a solid folder share its packed streams, so each of them is measured against the whole folder.
+fn packed_sizes(archive: &Archive) -> Vec<u64> {
+    let mut sizes = Vec::with_capacity(archive.files.len());
+    for (index, folder) in archive.folders.iter().enumerate() {
+        let first = archive.stream_map.folder_first_pack_stream_index[index];
+        let size = archive
+            .pack_sizes
+            .iter()
+            .skip(first)
+            .take(folder.packed_streams.len())
+            .fold(0u64, |sum, size| sum.saturating_add(*size));
+        sizes.extend(std::iter::repeat_n(size, folder.num_unpack_sub_streams));
+    }
+    sizes
+}
+
+impl<'a> SevenZipFile<'a> {
+    /// Attempts to decode the archive with the given password and processes each of its files.
+    fn process_with(&self, context: &Context, password: &str) -> Result<Vec<Segment>, Error> {
+        let mut archive = SevenZReader::new(
+            Cursor::new(self.0),
+            self.0.len() as u64,
+            Password::from(password),
+        )?;
+        context.check_members(archive.archive().files.len())?;
+        let packed_sizes = packed_sizes(archive.archive());
+        let mut segments = Vec::new();
+        // Errors that abandon the archive are kept aside, so the decoder does not mistake them for a wrong password.
+        let mut failure = None;
+        let mut ordinal = 0;
+        archive.for_each_entries(|entry, reader| {
+            ordinal += 1;
+            if entry.is_directory() {
+                return Ok(true);
+            }
+            // Files without content come last and have no folder to measure them against.
+            let packed_size = packed_sizes.get(ordinal - 1).copied();
+            let result = match context.read_member(&mut *reader, packed_size) {
+                Ok(buffer) => context.process_member(ordinal - 1, entry.name(), &buffer),
+                Err(e) if is_rejected(&e) => context.skip_member(entry.name(), e).and_then(|_| {
+                    // Solid blocks continue right after the entry, so its remainder has to be consumed.
+                    io::copy(reader, &mut io::sink())?;
+                    Ok(Vec::new())
+                }),
+                Err(e) => return Err(e.into()),
+            };
+            match result {
+                Ok(found) => {
+                    segments.extend(found);
+                    Ok(true)
+                }
+                Err(e) => {
+                    failure = Some(e);
+                    Ok(false)
+                }
+            }
+        })?;
+        match failure {
+            Some(e) => Err(Error::io(e)),
+            None => Ok(segments),
+        }
+    }
+}
+
+impl<'a> ProcessFile<'a> for SevenZipFile<'a> {
+    /// Attempts to parse the given byte slice as a 7z archive and processes each of its files.
+    ///
+    /// Entries are decoded in archive order, so solid blocks are decompressed only once.
+    /// Each file is measured against the packed size of its folder when the compression ratio is checked.
+    ///
+    /// Encrypted archives are decoded with each of the passwords in turn until one matches.
+    fn process(&'a self, context: &Context) -> io::Result<Vec<Segment>> {
+        let context = context.enter()?;
+        let passwords = context.passwords.iter().map(String::as_str);
+        for password in std::iter::once("").chain(passwords) {
+            let extracted = context.extracted.load(Ordering::Relaxed);
+            match self.process_with(&context, password) {
+                // A wrong password shows up as garbage that fails to decode or to match its checksum.
+                Err(Error::PasswordRequired) => {}
+                Err(Error::MaybeBadPassword(_) | Error::ChecksumVerificationFailed)
+                    if !password.is_empty() => {}
+                result => return result.map_err(to_io_error),
+            }
+            // Entries read before the failure do not count as extracted, they are read again with the next password.
+            context.extracted.store(extracted, Ordering::Relaxed);
+        }
+        Err(locked(context.passwords.len()))
+    }
+}
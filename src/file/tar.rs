@@ -0,0 +1,31 @@
+use super::{Context, ProcessFile, Segment};
+use ::tar::Archive;
+use std::io::{self, Cursor};
+
+/// Represents a tar archive as a byte slice reference.
+pub struct TarFile<'a>(pub(crate) &'a [u8]);
+
+impl<'a> ProcessFile<'a> for TarFile<'a> {
+    /// Attempts to parse the given byte slice as a tar archive and processes each of its regular files.
+    ///
+    /// Directories, links and device entries are skipped. The content of a regular file is detected on its own,
+    /// so a compressed dump or a nested archive inside the tar is unpacked as well, and its path becomes the member name.
+    fn process(&'a self, context: &Context) -> io::Result<Vec<Segment>> {
+        let context = context.enter()?;
+        let mut archive = Archive::new(Cursor::new(self.0));
+        let mut segments = Vec::new();
+        for (count, entry) in archive.entries()?.enumerate() {
+            context.check_members(count + 1)?;
+            let entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry.path()?.to_string_lossy().into_owned();
+            match context.read_member(entry, None) {
+                Ok(buffer) => segments.extend(context.process_member(count, &name, &buffer)?),
+                Err(e) => context.skip_member(&name, e)?,
+            }
+        }
+        Ok(segments)
+    }
+}
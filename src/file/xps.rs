@@ -0,0 +1,163 @@
+use super::{
+    epub::{mailto_address, parse_xml, read_entry, resolve, xml_text},
+    limit_error, Context, Location, ProcessFile, Segment,
+};
+use std::io::{self, Cursor};
+use zip::ZipArchive;
+
+/// Represents an XPS or OpenXPS document, which is a zip archive of fixed page markup, as a byte slice reference.
+pub struct XpsFile<'a>(pub(crate) &'a [u8]);
+
+/// Checks whether the given bytes are a zip archive holding a fixed document sequence.
+pub(crate) fn is_xps(bytes: &[u8]) -> bool {
+    ZipArchive::new(Cursor::new(bytes)).is_ok_and(|archive| archive.file_names().any(is_sequence))
+}
+
+/// Returns the name of the part an archive member belongs to, which for a piece of an interleaved part is its folder.
+fn part_name(name: &str) -> &str {
+    match name.rsplit_once('/') {
+        Some((part, piece)) if piece.starts_with('[') && piece.ends_with(".piece") => part,
+        _ => name,
+    }
+}
+
+/// Checks whether an archive member is the fixed document sequence, or a piece of it.
+fn is_sequence(name: &str) -> bool {
+    part_name(name).to_ascii_lowercase().ends_with(".fdseq")
+}
+
+/// Attempts to read the part at the given path, which may be stored interleaved as a sequence of pieces.
+///
+/// The pieces of an interleaved part are stored in a folder named after the part, as `[0].piece`, `[1].piece`
+/// and so on, up to the last one named like `[2].last.piece`. Their joined size counts against the member size.
+fn read_part(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    path: &str,
+    context: &Context,
+) -> io::Result<Vec<u8>> {
+    if archive.index_for_name(path).is_some() {
+        return read_entry(archive, path, context);
+    }
+    let mut bytes = Vec::new();
+    for i in 0.. {
+        let piece = format!("{}/[{}].piece", path, i);
+        let last = archive.index_for_name(&piece).is_none();
+        let piece = if last {
+            format!("{}/[{}].last.piece", path, i)
+        } else {
+            piece
+        };
+        bytes.extend(read_entry(archive, &piece, context)?);
+        let size = bytes.len() as u64;
+        if size > context.limits.max_member_size {
+            return Err(limit_error(&context.limits, false, size));
+        }
+        if last {
+            break;
+        }
+    }
+    Ok(bytes)
+}
+
+/// Returns the directory of an archive member.
+fn directory(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(directory, _)| directory)
+}
+
+/// Attempts to read the part at the given path and returns the resolved `Source` attributes of the given elements.
+fn sources(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    path: &str,
+    element: &str,
+    context: &Context,
+) -> io::Result<Vec<String>> {
+    let bytes = read_part(archive, path, context)?;
+    Ok(parse_xml(&xml_text(&bytes)?, path)?
+        .descendants()
+        .filter(|node| node.has_tag_name(element))
+        .filter_map(|node| node.attribute("Source"))
+        .map(|source| resolve(directory(path), source))
+        .collect())
+}
+
+/// Attempts to reassemble the text of a fixed page from its glyph runs.
+///
+/// Runs are joined in markup order, and a line break is inserted wherever the baseline changes.
+/// The addresses of `mailto:` links are added on lines of their own.
+fn page_text(bytes: &[u8], path: &str) -> io::Result<String> {
+    let markup = xml_text(bytes)?;
+    let document = parse_xml(&markup, path)?;
+    let mut text = String::new();
+    let mut baseline = None;
+    for node in document.descendants().filter(|node| node.is_element()) {
+        if let Some(address) = node
+            .attribute("FixedPage.NavigateUri")
+            .and_then(mailto_address)
+        {
+            text.push('\n');
+            text.push_str(&address);
+            text.push('\n');
+            baseline = None;
+        }
+        if !node.has_tag_name("Glyphs") {
+            continue;
+        }
+        let Some(run) = node.attribute("UnicodeString") else {
+            continue;
+        };
+        let origin = node.attribute("OriginY");
+        if baseline.is_some() && origin != baseline {
+            text.push('\n');
+        }
+        baseline = origin;
+        // A leading `{}` escapes strings that would otherwise start with a brace.
+        text.push_str(run.strip_prefix("{}").unwrap_or(run));
+    }
+    Ok(text)
+}
+
+impl<'a> ProcessFile<'a> for XpsFile<'a> {
+    /// Attempts to parse the given byte slice as an XPS document and extracts the text of each of its pages.
+    ///
+    /// Pages are found by following the fixed document sequence to its documents and their pages,
+    /// and are numbered in that order, starting at 1.
+    fn process(&'a self, context: &Context) -> io::Result<Vec<Segment>> {
+        let context = context.enter()?;
+        let mut archive = ZipArchive::new(Cursor::new(self.0))?;
+        context.check_members(archive.len())?;
+
+        let sequence = archive
+            .file_names()
+            .find(|name| is_sequence(name))
+            .map(|name| part_name(name).to_string())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Missing fixed document sequence",
+                )
+            })?;
+        let mut pages = Vec::new();
+        for document in sources(&mut archive, &sequence, "DocumentReference", &context)? {
+            match sources(&mut archive, &document, "PageContent", &context) {
+                Ok(found) => pages.extend(found),
+                Err(e) => context.skip_member(&document, e)?,
+            }
+        }
+
+        let mut segments = Vec::new();
+        for (i, page) in pages.iter().enumerate() {
+            match read_part(&mut archive, page, &context).and_then(|bytes| page_text(&bytes, page))
+            {
+                Ok(text) => segments.push(Segment {
+                    text,
+                    location: Location {
+                        page: Some(i + 1),
+                        ..Location::default()
+                    },
+                }),
+                Err(e) => context.skip_member(page, e)?,
+            }
+        }
+        Ok(segments)
+    }
+}
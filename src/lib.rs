@@ -0,0 +1,14 @@
+//! A blazingly fast library written in pure safe Rust to automatically extract email addresses from files.
+//!
+//! The `Extractor` finds email addresses in text, files and directories.
+//! Every match carries its `Source`, so the origin of each address can be traced back.
+//! The `file` module contains the machinery to detect and process the supported file types.
+
+mod emails;
+mod extractor;
+pub mod file;
+mod output;
+
+pub use emails::{Emails, Entry, Input, Match, Order, Role, Source};
+pub use extractor::{Extractor, Normalization, DEFAULT_PATTERN};
+pub use output::{write_emails, Format, OutputOptions};
@@ -1,68 +1,57 @@
-mod file;
-use env_logger::Builder;
-use file::TryIntoFileType;
+use email_address_extractor::{
+    file::Limits, write_emails, Emails, Extractor, Format, Normalization, Order, OutputOptions,
+};
+use env_logger::{Builder, Target};
 use log::{error, info, warn};
+use rayon::ThreadPoolBuilder;
 use std::{
-    collections::HashSet,
     env,
     fs::{self, File},
-    io::{self, BufReader, Read, Write},
-    path::PathBuf,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
 };
 
-/// Extracts email addresses from a list of strings using regex.
-///
-/// See: https://www.regular-expressions.info/email.html for a discussion about how to find an email address.
+/// Attempts to write the extracted emails to a file as described by the options.
 ///
-/// Uses a HashSet to handle deduplication inherently without sorting and deduping the list explicitly.
-fn extract_emails(content: &[String]) -> Vec<String> {
-    let regex = regex::Regex::new(r"(\b[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}\b)").unwrap(); // won't panic
-    content
-        .iter()
-        .flat_map(|line| regex.captures_iter(line).map(|cap| cap[0].to_string()))
-        .collect::<HashSet<_>>()
-        .into_iter()
-        .collect()
-}
+/// Without an output path, the file is called `emails` with the extension of the format and placed in the current directory.
+/// The output path `-` writes to standard output instead.
+fn write_emails_to_file(
+    emails: &Emails,
+    output_path: Option<&str>,
+    options: &OutputOptions,
+) -> io::Result<String> {
+    if output_path == Some("-") {
+        write_emails(emails, options, BufWriter::new(io::stdout().lock()))?;
+        return Ok(String::from("standard output"));
+    }
 
-/// Attempts to write the extracted emails to a plain text file.
-fn write_emails_to_file(emails: &[String], output_path: Option<&str>) -> io::Result<String> {
     let output_path = match output_path {
         Some(path) => PathBuf::from(path),
-        None => env::current_dir()?.join("emails.txt"),
+        None => env::current_dir()?.join(format!("emails.{}", options.format.extension())),
     };
 
-    let mut file = File::create(&output_path)?;
-
-    for email in emails {
-        writeln!(file, "{}", email)?;
-    }
+    let file = File::create(&output_path)?;
+    write_emails(emails, options, BufWriter::new(file))?;
 
-    output_path.to_str().map(String::from).ok_or_else(|| {
-        io::Error::new(
-            io::ErrorKind::Other,
-            "Failed to convert output path to string.",
-        )
-    })
+    output_path
+        .to_str()
+        .map(String::from)
+        .ok_or_else(|| io::Error::other("Failed to convert output path to string."))
 }
 
-/// Attempts to process the file from the given path and extract email addresses.
-fn process_file(input_path: &str, output_path: Option<&str>) -> io::Result<()> {
-    let metadata = fs::metadata(input_path)?;
-    info!("File path: {}.", input_path);
-    info!("File size: {} bytes.", metadata.len());
-
-    let file = File::open(input_path)?;
-    let mut reader = BufReader::new(file);
-    let mut buffer = vec![];
-    reader.read_to_end(&mut buffer)?;
-
-    let processed = buffer.try_into_filetype()?.process()?;
-    info!("File processed successfully.");
+/// Attempts to process all given input paths and write their combined email addresses to a single output.
+///
+/// Returns the number of given input files that failed to process.
+fn process_paths(
+    extractor: &Extractor,
+    input_paths: &[String],
+    output_path: Option<&str>,
+    options: &OutputOptions,
+) -> io::Result<usize> {
+    let emails = extractor.extract_paths(input_paths)?;
 
-    let emails = extract_emails(&processed);
     if !emails.is_empty() {
-        match write_emails_to_file(&emails, output_path) {
+        match write_emails_to_file(&emails, output_path, options) {
             Ok(path) => info!("Extracted emails written to {} successfully.", path),
             Err(e) => error!("Failed to write emails to file. {}.", e),
         }
@@ -70,27 +59,272 @@ fn process_file(input_path: &str, output_path: Option<&str>) -> io::Result<()> {
         warn!("No email address found.");
     }
 
-    Ok(())
+    Ok(emails.failed())
+}
+
+/// Attempts to read a password list with one password per line, ignoring empty lines.
+fn read_passwords(path: &str) -> io::Result<Vec<String>> {
+    let bytes = fs::read(path)?;
+    Ok(String::from_utf8_lossy(&bytes)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Attempts to take the value following the given option.
+fn next_value(args: &mut impl Iterator<Item = String>, option: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("Missing value for {}", option))
+}
+
+/// Attempts to take the value following the given option and parse it as a number.
+fn next_number<T: std::str::FromStr>(
+    args: &mut impl Iterator<Item = String>,
+    option: &str,
+) -> Result<T, String> {
+    let value = next_value(args, option)?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid number for {}: {}", option, value))
+}
+
+/// Command line arguments passed to the application.
+struct Args {
+    input_paths: Vec<String>,
+    output_path: Option<String>,
+    threads: Option<usize>,
+    normalization: Normalization,
+    pattern: Option<String>,
+    unsupported_as_text: bool,
+    counting: bool,
+    max_depth: Option<usize>,
+    passwords: Vec<String>,
+    password_files: Vec<String>,
+    limits: Limits,
+    output: OutputOptions,
+}
+
+impl Args {
+    /// Attempts to collect the passwords given directly, followed by those read from the password files.
+    fn all_passwords(&self) -> Result<Vec<String>, String> {
+        let mut passwords = self.passwords.clone();
+        for path in &self.password_files {
+            let list = read_passwords(path)
+                .map_err(|e| format!("Failed to read password file {}. {}", path, e))?;
+            passwords.extend(list);
+        }
+        Ok(passwords)
+    }
+
+    /// Attempts to parse the command line arguments, excluding the program name.
+    ///
+    /// Every argument that is not an option is treated as an input path.
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+        let mut input_paths = Vec::new();
+        let mut output_path = None;
+        let mut threads = None;
+        let mut normalization = Normalization::None;
+        let mut pattern = None;
+        let mut unsupported_as_text = false;
+        let mut counting = false;
+        let mut max_depth = None;
+        let mut passwords = Vec::new();
+        let mut password_files = Vec::new();
+        let mut limits = Limits::default();
+        let mut output = OutputOptions::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-o" | "--output" => {
+                    output_path = Some(next_value(&mut args, &arg)?);
+                }
+                "-t" | "--threads" => {
+                    let count = next_value(&mut args, &arg)?;
+                    let count = count
+                        .parse()
+                        .map_err(|_| format!("Invalid thread count: {}", count))?;
+                    threads = Some(count);
+                }
+                "-n" | "--normalize" => {
+                    let mode = next_value(&mut args, &arg)?;
+                    normalization = match mode.as_str() {
+                        "none" => Normalization::None,
+                        "domain" => Normalization::Domain,
+                        "lowercase" => Normalization::Lowercase,
+                        _ => return Err(format!("Invalid normalization: {}", mode)),
+                    };
+                }
+                "-f" | "--format" => {
+                    let name = next_value(&mut args, &arg)?;
+                    output.format = match name.as_str() {
+                        "txt" => Format::Txt,
+                        "csv" => Format::Csv,
+                        "jsonl" => Format::Jsonl,
+                        "json" => Format::Json,
+                        _ => return Err(format!("Invalid format: {}", name)),
+                    };
+                }
+                "-p" | "--pattern" => {
+                    pattern = Some(next_value(&mut args, &arg)?);
+                }
+                "--unsupported-as-text" => unsupported_as_text = true,
+                "--provenance" => output.provenance = true,
+                "-c" | "--count" => counting = true,
+                "--max-depth" => {
+                    let depth = next_value(&mut args, &arg)?;
+                    let depth = depth
+                        .parse()
+                        .map_err(|_| format!("Invalid nesting depth: {}", depth))?;
+                    max_depth = Some(depth);
+                }
+                "--max-total-size" => limits.max_total_size = next_number(&mut args, &arg)?,
+                "--max-member-size" => limits.max_member_size = next_number(&mut args, &arg)?,
+                "--max-ratio" => limits.max_ratio = next_number(&mut args, &arg)?,
+                "--max-members" => limits.max_members = next_number(&mut args, &arg)?,
+                "--password" => passwords.push(next_value(&mut args, &arg)?),
+                "--password-file" => password_files.push(next_value(&mut args, &arg)?),
+                "-s" | "--sort" => {
+                    let name = next_value(&mut args, &arg)?;
+                    output.order = match name.as_str() {
+                        "lexical" => Order::Lexical,
+                        "domain" => Order::Domain,
+                        "first" => Order::FirstSeen,
+                        "frequency" => Order::Frequency,
+                        _ => return Err(format!("Invalid order: {}", name)),
+                    };
+                }
+                option if option.starts_with('-') && option.len() > 1 => {
+                    return Err(format!("Unknown option: {}", option));
+                }
+                _ => input_paths.push(arg),
+            }
+        }
+        if input_paths.is_empty() {
+            return Err(String::from("Missing input path"));
+        }
+        // Earlier versions took the output path as second argument, which would now be read as an input.
+        if let [_, second] = input_paths.as_slice() {
+            if output_path.is_none() && second != "-" && !Path::new(second).exists() {
+                return Err(format!(
+                    "Input path {} does not exist. An output path has to be given with -o, e.g. \"-o {}\"",
+                    second, second
+                ));
+            }
+        }
+        Ok(Args {
+            input_paths,
+            output_path,
+            threads,
+            normalization,
+            pattern,
+            unsupported_as_text,
+            counting,
+            max_depth,
+            passwords,
+            password_files,
+            limits,
+            output: OutputOptions {
+                counts: counting,
+                ..output
+            },
+        })
+    }
 }
 
 fn main() {
+    // Logs go to stderr, so that stdout stays clean for the extracted emails.
     Builder::from_default_env()
+        .target(Target::Stderr)
         .write_style(env_logger::WriteStyle::Always)
         .filter_level(log::LevelFilter::Trace)
         .init();
 
-    let args: Vec<String> = env::args().collect();
+    let mut args = env::args();
+    let program = args.next().unwrap_or_default();
 
-    if args.len() < 2 || args.len() > 3 {
-        error!("Usage: \"{} <input_path> [output_path]\".", args[0]);
+    let args = match Args::parse(args) {
+        Ok(args) => args,
+        Err(e) => {
+            error!("{}.", e);
+            error!("Usage: \"{} <input_path>... [options]\".", program);
+            std::process::exit(1);
+        }
+    };
+
+    // Zero threads lets rayon pick the number of available CPU cores.
+    if let Err(e) = ThreadPoolBuilder::new()
+        .num_threads(args.threads.unwrap_or(0))
+        .build_global()
+    {
+        error!("Failed to set up thread pool. {}.", e);
         std::process::exit(1);
     }
 
-    let input_path = &args[1];
-    let output_path = args.get(2).map(|s| s.as_str());
+    let passwords = match args.all_passwords() {
+        Ok(passwords) => passwords,
+        Err(e) => {
+            error!("{}.", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut extractor = Extractor::new()
+        .with_normalization(args.normalization)
+        .with_unsupported_as_text(args.unsupported_as_text)
+        .with_counting(args.counting)
+        .with_passwords(passwords)
+        .with_limits(args.limits);
+    if let Some(max_depth) = args.max_depth {
+        extractor = extractor.with_max_depth(max_depth);
+    }
+    if let Some(pattern) = &args.pattern {
+        extractor = match extractor.with_pattern(pattern) {
+            Ok(extractor) => extractor,
+            Err(e) => {
+                error!("Invalid pattern. {}.", e);
+                std::process::exit(1);
+            }
+        };
+    }
+
+    match process_paths(
+        &extractor,
+        &args.input_paths,
+        args.output_path.as_deref(),
+        &args.output,
+    ) {
+        Ok(0) => {}
+        Ok(failed) => {
+            error!("Failed to process {} of the given input files.", failed);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            error!("Application error: {}.", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if let Err(e) = process_file(input_path, output_path) {
-        error!("Application error: {}.", e);
-        std::process::exit(1);
+    #[test]
+    fn tries_passwords_before_password_files() {
+        let path = env::temp_dir().join(format!(
+            "email-address-extractor-passwords-{}",
+            std::process::id()
+        ));
+        fs::write(&path, "listed\n\nlast\n").unwrap();
+        let args = [
+            "leak.zip",
+            "--password-file",
+            path.to_str().unwrap(),
+            "--password",
+            "given",
+        ];
+        let args = Args::parse(args.into_iter().map(String::from)).unwrap();
+        assert_eq!(args.all_passwords().unwrap(), ["given", "listed", "last"]);
+        fs::remove_file(path).unwrap();
     }
 }
@@ -0,0 +1,189 @@
+use crate::emails::{Emails, Entry, Order};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::Path,
+    sync::Arc,
+};
+
+/// Represents the formats the extracted emails can be written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// One address per line.
+    #[default]
+    Txt,
+    /// Comma separated values with a header row.
+    Csv,
+    /// One JSON object per line.
+    Jsonl,
+    /// A single JSON array of objects.
+    Json,
+}
+
+impl Format {
+    /// Returns the file extension commonly used for the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Txt => "txt",
+            Format::Csv => "csv",
+            Format::Jsonl => "jsonl",
+            Format::Json => "json",
+        }
+    }
+}
+
+/// Describes how the extracted emails are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OutputOptions {
+    /// The format the emails are written in.
+    pub format: Format,
+    /// The order the emails are written in.
+    pub order: Order,
+    /// Appends the source of the first occurrence to each address in plain text.
+    pub provenance: bool,
+    /// Appends the occurrence count to each address in plain text.
+    pub counts: bool,
+}
+
+/// Representation of an entry used by the structured formats.
+#[derive(Serialize)]
+struct Record<'a> {
+    address: &'a str,
+    normalized: &'a str,
+    count: u64,
+    path: String,
+    member: Option<&'a str>,
+    page: Option<usize>,
+    line: Option<usize>,
+    offset: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    roles: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<BTreeMap<String, u64>>,
+}
+
+impl<'a> Record<'a> {
+    /// Borrows the fields of the given entry as a record, including its per-file counts if requested.
+    fn new(normalized: &'a str, entry: &'a Entry, files: bool) -> Record<'a> {
+        Record {
+            address: &entry.address,
+            normalized,
+            count: entry.count,
+            path: display_path(&entry.source.path),
+            member: entry.source.member.as_deref(),
+            page: entry.source.page,
+            line: entry.source.line,
+            offset: entry.source.offset,
+            roles: entry.roles.iter().map(|role| role.as_str()).collect(),
+            files: files.then(|| {
+                entry
+                    .files
+                    .iter()
+                    .map(|(path, count)| (display_path(path), *count))
+                    .collect()
+            }),
+        }
+    }
+
+    /// Converts the record into the fields of a csv row.
+    ///
+    /// Roles are joined into a single field separated by `; `.
+    /// Per-file counts are joined into a single field as `path=count` pairs separated by `; `.
+    fn into_csv(self) -> Vec<String> {
+        let optional = |value: Option<usize>| value.map(|v| v.to_string()).unwrap_or_default();
+        let mut fields = vec![
+            self.address.to_string(),
+            self.normalized.to_string(),
+            self.count.to_string(),
+            self.path,
+            self.member.unwrap_or_default().to_string(),
+            optional(self.page),
+            optional(self.line),
+            self.offset.to_string(),
+            self.roles.join("; "),
+        ];
+        if let Some(files) = self.files {
+            let files: Vec<String> = files
+                .iter()
+                .map(|(path, count)| format!("{}={}", path, count))
+                .collect();
+            fields.push(files.join("; "));
+        }
+        fields
+    }
+}
+
+/// Formats an optional input path, using `-` for inputs without a path.
+fn display_path(path: &Option<Arc<Path>>) -> String {
+    match path {
+        Some(path) => path.to_string_lossy().into_owned(),
+        None => String::from("-"),
+    }
+}
+
+/// Attempts to write the emails to the given writer as described by the options.
+///
+/// The structured formats always include the normalized form, the occurrence count and the source of each address,
+/// as well as the roles of addresses found in email messages.
+/// If the emails were counted per input file, the structured formats include these counts as well.
+pub fn write_emails<W: Write>(
+    emails: &Emails,
+    options: &OutputOptions,
+    mut writer: W,
+) -> io::Result<()> {
+    let entries = emails.sorted(options.order);
+    let files = emails.counts_files();
+    match options.format {
+        Format::Txt => {
+            for (email, entry) in entries {
+                write!(writer, "{}", email)?;
+                if options.counts {
+                    write!(writer, "\t{}", entry.count)?;
+                }
+                if options.provenance {
+                    write!(writer, "\t{}", entry.source)?;
+                }
+                writeln!(writer)?;
+            }
+        }
+        Format::Csv => {
+            let mut csv = csv::Writer::from_writer(&mut writer);
+            let mut header = vec![
+                "address",
+                "normalized",
+                "count",
+                "path",
+                "member",
+                "page",
+                "line",
+                "offset",
+                "roles",
+            ];
+            if files {
+                header.push("files");
+            }
+            csv.write_record(header)?;
+            for (email, entry) in entries {
+                csv.write_record(Record::new(email, entry, files).into_csv())?;
+            }
+            csv.flush()?;
+        }
+        Format::Jsonl => {
+            for (email, entry) in entries {
+                serde_json::to_writer(&mut writer, &Record::new(email, entry, files))?;
+                writeln!(writer)?;
+            }
+        }
+        Format::Json => {
+            // Writes the array element by element to avoid building the whole document in memory.
+            write!(writer, "[")?;
+            for (i, (email, entry)) in entries.into_iter().enumerate() {
+                write!(writer, "{}\n  ", if i == 0 { "" } else { "," })?;
+                serde_json::to_writer(&mut writer, &Record::new(email, entry, files))?;
+            }
+            writeln!(writer, "\n]")?;
+        }
+    }
+    writer.flush()
+}
@@ -0,0 +1,52 @@
+// Every test crate uses only some of the helpers.
+#![allow(dead_code)]
+
+use email_address_extractor::{Emails, Entry, Extractor, Input, Order};
+use std::{fs, io, io::Cursor};
+
+/// Reads the fixture with the given file name.
+pub fn fixture(name: &str) -> Vec<u8> {
+    fs::read(format!(
+        "{}/tests/fixtures/{}",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    ))
+    .unwrap()
+}
+
+/// Extracts the addresses of the given bytes, as if they were read from standard input.
+pub fn extract(bytes: &[u8]) -> io::Result<Emails> {
+    Extractor::new().extract_reader(Cursor::new(bytes), &Input::default())
+}
+
+/// Returns the entry of the given address, which has to be found.
+pub fn entry<'a>(emails: &'a Emails, address: &str) -> &'a Entry {
+    emails
+        .iter()
+        .find(|(email, _)| *email == address)
+        .map(|(_, entry)| entry)
+        .unwrap()
+}
+
+/// Returns the addresses found in the given bytes in lexical order.
+pub fn addresses(bytes: &[u8]) -> Vec<String> {
+    extract(bytes)
+        .unwrap()
+        .sorted(Order::Lexical)
+        .into_iter()
+        .map(|(email, _)| email.clone())
+        .collect()
+}
+
+/// Feeds truncated and corrupted copies of the given file to the extractor, which may fail but must not panic.
+pub fn assert_survives_corruption(bytes: &[u8]) {
+    let step = bytes.len() / 100 + 1;
+    for position in (0..bytes.len()).step_by(step) {
+        let _ = extract(&bytes[..position]);
+        let mut corrupted = bytes.to_vec();
+        for byte in corrupted.iter_mut().skip(position).take(4) {
+            *byte ^= 0xFF;
+        }
+        let _ = extract(&corrupted);
+    }
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use common::{entry, extract};
+use email_address_extractor::Order;
+use std::io::{Cursor, Write};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+const CONTAINER: &str = r#"<?xml version="1.0"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>"#;
+
+const PACKAGE: &str = r#"<?xml version="1.0"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <manifest>
+    <item id="one" href="one.xhtml" media-type="application/xhtml+xml"/>
+    <item id="two" href="two.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+  <spine>
+    <itemref idref="one"/>
+    <itemref idref="two"/>
+  </spine>
+</package>"#;
+
+/// Builds an e-book with the given chapters.
+fn epub(chapters: [&str; 2]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    // The media type has to be stored uncompressed at the start, where it tells e-books apart from other archives.
+    let stored = options.compression_method(CompressionMethod::Stored);
+    writer.start_file("mimetype", stored).unwrap();
+    writer.write_all(b"application/epub+zip").unwrap();
+    for (name, content) in [
+        ("META-INF/container.xml", CONTAINER),
+        ("OEBPS/content.opf", PACKAGE),
+        ("OEBPS/one.xhtml", chapters[0]),
+        ("OEBPS/two.xhtml", chapters[1]),
+    ] {
+        writer.start_file(name, options).unwrap();
+        writer.write_all(content.as_bytes()).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn decodes_chapter_text() {
+    let emails = extract(&epub([
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml">
+  <head><title>One</title><style>p { color: red; }</style></head>
+  <body>
+    <p>Write to <b>ent&#64;example.com</b> or <a href="mailto:link%40example.com">us</a>.</p>
+    <p>contact</p><p>first@example.com</p>
+  </body>
+</html>"#,
+        r#"<html xmlns="http://www.w3.org/1999/xhtml"><body><p>Copyright&nbsp;owner@example.com</p></body></html>"#,
+    ]))
+    .unwrap();
+    let sorted = emails.sorted(Order::Lexical);
+    let addresses: Vec<&str> = sorted.iter().map(|(email, _)| email.as_str()).collect();
+    assert_eq!(
+        addresses,
+        [
+            "ent@example.com",
+            "first@example.com",
+            "link@example.com",
+            "owner@example.com"
+        ]
+    );
+    let member = |address| entry(&emails, address).source.member.as_deref();
+    assert_eq!(member("ent@example.com"), Some("OEBPS/one.xhtml"));
+    assert_eq!(member("owner@example.com"), Some("OEBPS/two.xhtml"));
+}
@@ -0,0 +1,254 @@
+mod common;
+
+use common::entry;
+use email_address_extractor::{file::Limits, Emails, Extractor, Input, Order};
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    fs,
+    io::{self, Cursor, Write},
+    path::PathBuf,
+};
+use tar::{Builder, Header};
+use zip::{unstable::write::FileOptionsExt, write::SimpleFileOptions, AesMode, ZipWriter};
+
+/// Creates an empty scratch directory for a test.
+fn scratch(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!(
+        "email-address-extractor-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+#[test]
+fn skips_failed_input_and_keeps_others() {
+    let directory = scratch("failed-input");
+    let dump = directory.join("dump.sql");
+    let image = directory.join("image.png");
+    fs::write(&dump, "INSERT INTO users VALUES ('alice@example.com');\n").unwrap();
+    fs::write(&image, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+    let emails = Extractor::new().extract_paths(&[&dump, &image]).unwrap();
+    assert!(emails.contains("alice@example.com"));
+    assert_eq!(emails.failed(), 1);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn aborts_on_missing_input() {
+    let directory = scratch("missing-input");
+    let dump = directory.join("dump.sql");
+    fs::write(&dump, "alice@example.com\n").unwrap();
+
+    let result = Extractor::new().extract_paths(&[dump, directory.join("missing.txt")]);
+    assert!(result.is_err());
+    fs::remove_dir_all(directory).unwrap();
+}
+
+/// Compresses the given bytes with gzip.
+fn gzip(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Extracts the given bytes with a member size limit of 64 KiB.
+fn extract_limited(bytes: Vec<u8>) -> io::Result<Emails> {
+    let limits = Limits {
+        max_member_size: 64 * 1024,
+        ..Limits::default()
+    };
+    Extractor::new()
+        .with_limits(limits)
+        .extract_reader(Cursor::new(bytes), &Input::default())
+}
+
+/// Builds a text dump of the given size that ends with an email address.
+fn dump(size: usize) -> Vec<u8> {
+    let mut text = "filler text\n".repeat(size / 12).into_bytes();
+    text.extend_from_slice(b"alice@example.com\n");
+    text
+}
+
+#[test]
+fn streams_compressed_text_beyond_member_size() {
+    let emails = extract_limited(gzip(&dump(1024 * 1024))).unwrap();
+    assert!(emails.contains("alice@example.com"));
+    assert_eq!(emails.failed(), 0);
+}
+
+#[test]
+fn keeps_addresses_of_streamed_text_cut_off_by_ratio() {
+    let mut text = b"bob@example.com\n".to_vec();
+    text.extend(dump(1024 * 1024));
+    let limits = Limits {
+        max_ratio: 10,
+        ..Limits::default()
+    };
+    let emails = Extractor::new()
+        .with_limits(limits)
+        .extract_reader(Cursor::new(gzip(&text)), &Input::default())
+        .unwrap();
+    assert!(emails.contains("bob@example.com"));
+    assert!(!emails.contains("alice@example.com"));
+    assert_eq!(emails.failed(), 1);
+}
+
+#[test]
+fn streams_tar_entries_beyond_member_size() {
+    let mut builder = Builder::new(Vec::new());
+    for (name, text) in [("large.txt", dump(1024 * 1024)), ("small.txt", dump(1024))] {
+        let mut header = Header::new_gnu();
+        header.set_size(text.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, name, text.as_slice())
+            .unwrap();
+    }
+    let emails = extract_limited(builder.into_inner().unwrap()).unwrap();
+    let sorted = emails.sorted(Order::FirstSeen);
+    assert_eq!(sorted.len(), 1);
+    assert_eq!(sorted[0].1.source.member.as_deref(), Some("large.txt"));
+    assert_eq!(sorted[0].1.count, 2);
+}
+
+/// Builds a zip archive with the given members, each written with its own options.
+fn zip_with(members: &[(&str, &[u8], SimpleFileOptions)]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, content, options) in members {
+        writer.start_file(*name, *options).unwrap();
+        writer.write_all(content).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+/// Builds a zip archive with the given members.
+fn zip(members: &[(&str, &[u8])]) -> Vec<u8> {
+    let members: Vec<_> = members
+        .iter()
+        .map(|(name, content)| (*name, *content, SimpleFileOptions::default()))
+        .collect();
+    zip_with(&members)
+}
+
+/// Builds a zip archive holding a text member and a nested archive with another text member.
+fn nested_zip() -> Vec<u8> {
+    let inner = zip(&[("users.csv", b"id,email\n1,nested@example.com\n")]);
+    zip(&[("top.txt", b"top@example.com"), ("inner.zip", &inner)])
+}
+
+#[test]
+fn recurses_into_nested_archives() {
+    let emails = Extractor::new()
+        .extract_reader(Cursor::new(nested_zip()), &Input::default())
+        .unwrap();
+    let nested = entry(&emails, "nested@example.com");
+    assert_eq!(nested.source.member.as_deref(), Some("inner.zip/users.csv"));
+    assert_eq!(&*nested.source.ordinals, [1, 0]);
+    assert!(emails.contains("top@example.com"));
+}
+
+#[test]
+fn skips_archives_beyond_max_depth() {
+    let emails = Extractor::new()
+        .with_max_depth(1)
+        .extract_reader(Cursor::new(nested_zip()), &Input::default())
+        .unwrap();
+    assert!(emails.contains("top@example.com"));
+    assert!(!emails.contains("nested@example.com"));
+}
+
+/// Builds a zip archive with an AES member encrypted with `aes`, a ZipCrypto member encrypted with `zipcrypto`
+/// and a plain member.
+fn encrypted_zip(aes: &'static str, zipcrypto: &str) -> Vec<u8> {
+    let options = SimpleFileOptions::default();
+    zip_with(&[
+        (
+            "aes.txt",
+            b"aes@example.com",
+            options.with_aes_encryption(AesMode::Aes256, aes),
+        ),
+        (
+            "zipcrypto.txt",
+            b"zipcrypto@example.com",
+            options.with_deprecated_encryption(zipcrypto.as_bytes()),
+        ),
+        ("plain.txt", b"plain@example.com", options),
+    ])
+}
+
+/// Extracts the addresses of the given bytes with the given passwords and limits.
+fn extract_with_passwords(bytes: Vec<u8>, passwords: &[&str], limits: Limits) -> Vec<String> {
+    Extractor::new()
+        .with_passwords(passwords.iter().map(|password| password.to_string()))
+        .with_limits(limits)
+        .extract_reader(Cursor::new(bytes), &Input::default())
+        .unwrap()
+        .sorted(Order::Lexical)
+        .into_iter()
+        .map(|(email, _)| email.clone())
+        .collect()
+}
+
+#[test]
+fn decrypts_zipcrypto_and_aes_members() {
+    let addresses = extract_with_passwords(
+        encrypted_zip("secret", "secret"),
+        &["wrong", "secret"],
+        Limits::default(),
+    );
+    assert_eq!(
+        addresses,
+        [
+            "aes@example.com",
+            "plain@example.com",
+            "zipcrypto@example.com"
+        ]
+    );
+}
+
+#[test]
+fn decrypts_members_with_different_passwords() {
+    // The password matching the first member is tried first for the second one, which has to fall back to the others.
+    let addresses = extract_with_passwords(
+        encrypted_zip("first", "second"),
+        &["second", "first"],
+        Limits::default(),
+    );
+    assert_eq!(addresses.len(), 3);
+}
+
+#[test]
+fn skips_locked_members() {
+    for passwords in [&[][..], &["wrong"]] {
+        let addresses = extract_with_passwords(
+            encrypted_zip("secret", "secret"),
+            passwords,
+            Limits::default(),
+        );
+        assert_eq!(addresses, ["plain@example.com"]);
+    }
+}
+
+#[test]
+fn keeps_trying_passwords_after_garbage_exceeds_limits() {
+    // The wrong password passes the ZipCrypto check byte of this member, and its garbage inflates beyond 400 bytes.
+    let limits = Limits {
+        max_member_size: 400,
+        ..Limits::default()
+    };
+    let bytes = fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/zipcrypto.zip"
+    ))
+    .unwrap();
+    assert!(extract_with_passwords(bytes.clone(), &["wrong227"], limits).is_empty());
+    assert_eq!(
+        extract_with_passwords(bytes, &["wrong227", "secret"], limits),
+        ["alice@example.com"]
+    );
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use common::entry;
+use email_address_extractor::{
+    file::{Limits, MboxStream},
+    Extractor, Input,
+};
+use std::io::Cursor;
+
+#[test]
+fn keeps_first_message_of_mailbox_as_source() {
+    let mut mailbox = String::new();
+    for number in 1..=12 {
+        let body = match number {
+            2 | 10 => "Write to shared@example.com",
+            _ => "Nothing to see",
+        };
+        mailbox.push_str(&format!(
+            "From sender@example.com Mon Jan  1 00:00:00 2024\n\
+            From: sender@example.com\nSubject: Message {}\n\n{}\n\n",
+            number, body
+        ));
+    }
+    let emails = Extractor::new()
+        .extract_reader(Cursor::new(mailbox), &Input::default())
+        .unwrap();
+    let entry = entry(&emails, "shared@example.com");
+    assert_eq!(entry.source.member.as_deref(), Some("message 2"));
+    assert_eq!(&*entry.source.ordinals, [1]);
+}
+
+/// Builds a mailbox whose second message holds a single line of the given length.
+fn mailbox_with_long_line(length: usize) -> Vec<u8> {
+    let mut mailbox = Vec::new();
+    for (number, body) in [
+        (1, String::from("first@example.com")),
+        (2, format!("hidden@example.com {}", "a".repeat(length))),
+        (3, String::from("third@example.com")),
+    ] {
+        mailbox.extend_from_slice(
+            format!(
+                "From sender@example.com Mon Jan  1 00:00:00 2024\n\
+                From: sender@example.com\nSubject: Message {}\n\n{}\n\n",
+                number, body
+            )
+            .as_bytes(),
+        );
+    }
+    mailbox
+}
+
+#[test]
+fn skips_messages_exceeding_member_size() {
+    let limits = Limits {
+        max_member_size: 64 * 1024,
+        ..Limits::default()
+    };
+    let input = Input::default();
+    let extractor = Extractor::new().with_limits(limits);
+    let emails = extractor
+        .extract_reader(Cursor::new(mailbox_with_long_line(1024 * 1024)), &input)
+        .unwrap();
+    assert!(emails.contains("first@example.com"));
+    assert!(!emails.contains("hidden@example.com"));
+    assert!(emails.contains("third@example.com"));
+
+    let emails = extractor
+        .extract_reader(Cursor::new(mailbox_with_long_line(1024)), &input)
+        .unwrap();
+    assert!(emails.contains("hidden@example.com"));
+}
+
+#[test]
+fn unquotes_from_lines_of_mboxrd_messages() {
+    let mailbox = b"From sender@example.com Mon Jan  1 00:00:00 2024\n\
+        From: sender@example.com\n\n>From the start\n>>From here\nNot >From here\n\n\
+        From sender@example.com Mon Jan  1 00:00:00 2024\n\
+        From: sender@example.com\n\nSecond\n";
+    let mut messages = Vec::new();
+    MboxStream::new(&mailbox[..], u64::MAX)
+        .process(|number, message| {
+            messages.push((number, String::from_utf8(message?.to_vec()).unwrap()));
+            Ok(())
+        })
+        .unwrap();
+    assert_eq!(
+        messages,
+        [
+            (
+                1,
+                String::from(
+                    "From: sender@example.com\n\nFrom the start\n>From here\nNot >From here\n\n"
+                )
+            ),
+            (2, String::from("From: sender@example.com\n\nSecond\n"))
+        ]
+    );
+}
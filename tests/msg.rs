@@ -0,0 +1,49 @@
+mod common;
+
+use common::{addresses, assert_survives_corruption, entry, extract, fixture};
+use email_address_extractor::Role;
+
+#[test]
+fn extracts_message_fields_and_attachments() {
+    assert_eq!(
+        addresses(&fixture("mail.msg")),
+        [
+            "att@example.com",
+            "body.inner@example.com",
+            "body.top@example.com",
+            "orig@example.com",
+            "r0.inner@example.com",
+            "r0.top@example.com",
+            "r1.inner@example.com",
+            "r1.top@example.com",
+            "r2.inner@example.com",
+            "r2.top@example.com",
+            "sender.inner@example.com",
+            "sender.top@example.com",
+            "subj@example.com"
+        ]
+    );
+}
+
+#[test]
+fn tags_recipients_and_names_embedded_message() {
+    let emails = extract(&fixture("mail.msg")).unwrap();
+    let entry = |address| entry(&emails, address);
+    assert!(entry("sender.top@example.com")
+        .roles
+        .contains(&Role::Sender));
+    assert!(entry("r1.top@example.com").roles.contains(&Role::Cc));
+    assert!(entry("r2.top@example.com").roles.contains(&Role::Bcc));
+    let inner = entry("r0.inner@example.com");
+    assert!(inner.roles.contains(&Role::Recipient));
+    assert_eq!(inner.source.member.as_deref(), Some("Forwarded"));
+    assert_eq!(
+        entry("att@example.com").source.member.as_deref(),
+        Some("notes.txt")
+    );
+}
+
+#[test]
+fn survives_malformed_messages() {
+    assert_survives_corruption(&fixture("mail.msg"));
+}
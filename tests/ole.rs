@@ -0,0 +1,59 @@
+mod common;
+
+use common::{addresses, assert_survives_corruption, fixture};
+use email_address_extractor::{file::Limits, Extractor, Input};
+use std::io::Cursor;
+
+#[test]
+fn extracts_word_document_text() {
+    assert_eq!(
+        addresses(&fixture("legacy.doc")),
+        ["alice@example.com", "bob@example.org"]
+    );
+}
+
+#[test]
+fn extracts_excel_workbook_strings() {
+    assert_eq!(
+        addresses(&fixture("legacy.xls")),
+        [
+            "frank@example.com",
+            "grace@example.com",
+            "heidi@example.com",
+            "ivan@example.com"
+        ]
+    );
+}
+
+#[test]
+fn extracts_power_point_text_atoms() {
+    assert_eq!(
+        addresses(&fixture("legacy.ppt")),
+        ["carol@example.net", "dave@example.com", "erin@example.com"]
+    );
+}
+
+#[test]
+fn survives_malformed_documents() {
+    for name in ["legacy.doc", "legacy.xls", "legacy.ppt"] {
+        assert_survives_corruption(&fixture(name));
+    }
+}
+
+#[test]
+fn limits_document_text() {
+    let limits = Limits {
+        max_member_size: 16,
+        ..Limits::default()
+    };
+    for name in ["legacy.doc", "legacy.xls", "legacy.ppt"] {
+        let error = Extractor::new()
+            .with_limits(limits)
+            .extract_reader(Cursor::new(fixture(name)), &Input::default())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Document text exceeds the limit of 16 bytes"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use email_address_extractor::{
+    write_emails, Emails, Extractor, Format, Input, Order, OutputOptions,
+};
+use std::path::Path;
+
+/// Extracts the addresses of a small text file.
+fn emails() -> Emails {
+    Extractor::new()
+        .extract_bytes(
+            b"bob@example.com\nalice@example.com bob@example.com\n",
+            &Input::default(),
+        )
+        .unwrap()
+}
+
+/// Writes the emails in the given format in lexical order.
+fn write(emails: &Emails, format: Format) -> String {
+    let options = OutputOptions {
+        format,
+        ..OutputOptions::default()
+    };
+    let mut output = Vec::new();
+    write_emails(emails, &options, &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn writes_csv_with_header() {
+    assert_eq!(
+        write(&emails(), Format::Csv),
+        "address,normalized,count,path,member,page,line,offset,roles\n\
+         alice@example.com,alice@example.com,1,-,,,2,16,\n\
+         bob@example.com,bob@example.com,2,-,,,1,0,\n"
+    );
+}
+
+#[test]
+fn writes_one_json_object_per_line() {
+    assert_eq!(
+        write(&emails(), Format::Jsonl),
+        "{\"address\":\"alice@example.com\",\"normalized\":\"alice@example.com\",\"count\":1,\"path\":\"-\",\"member\":null,\"page\":null,\"line\":2,\"offset\":16}\n\
+         {\"address\":\"bob@example.com\",\"normalized\":\"bob@example.com\",\"count\":2,\"path\":\"-\",\"member\":null,\"page\":null,\"line\":1,\"offset\":0}\n"
+    );
+}
+
+#[test]
+fn writes_json_array() {
+    let output = write(&emails(), Format::Json);
+    assert!(output.starts_with("[\n  {"));
+    assert!(output.ends_with("}\n]\n"));
+    let records: Vec<serde_json::Value> = serde_json::from_str(&output).unwrap();
+    let addresses: Vec<&str> = records
+        .iter()
+        .map(|record| record["address"].as_str().unwrap())
+        .collect();
+    assert_eq!(addresses, ["alice@example.com", "bob@example.com"]);
+    assert_eq!(records[1]["count"], 2);
+}
+
+#[test]
+fn writes_empty_json_array() {
+    let output = write(&Emails::new(), Format::Json);
+    let records: Vec<serde_json::Value> = serde_json::from_str(&output).unwrap();
+    assert!(records.is_empty());
+}
+
+/// Extracts the addresses of two small text files in counting mode.
+fn counted() -> Emails {
+    let extractor = Extractor::new().with_counting(true);
+    let first = Input {
+        index: 0,
+        path: Some(Path::new("first.txt").into()),
+    };
+    let second = Input {
+        index: 1,
+        path: Some(Path::new("second.txt").into()),
+    };
+    let first = extractor
+        .extract_bytes(b"alice@example.com bob@example.com bob@example.com", &first)
+        .unwrap();
+    let second = extractor
+        .extract_bytes(b"bob@example.com carol@example.com", &second)
+        .unwrap();
+    first.merge(second)
+}
+
+#[test]
+fn counts_occurrences_per_file() {
+    let emails = counted();
+    assert!(emails.counts_files());
+    let (_, bob) = emails
+        .iter()
+        .find(|(email, _)| *email == "bob@example.com")
+        .unwrap();
+    assert_eq!(bob.count, 3);
+    let files: Vec<(&Path, u64)> = bob
+        .files
+        .iter()
+        .map(|(path, count)| (path.as_deref().unwrap(), *count))
+        .collect();
+    assert_eq!(
+        files,
+        [(Path::new("first.txt"), 2), (Path::new("second.txt"), 1)]
+    );
+}
+
+#[test]
+fn writes_counts_in_frequency_order() {
+    let options = OutputOptions {
+        order: Order::Frequency,
+        counts: true,
+        ..OutputOptions::default()
+    };
+    let mut output = Vec::new();
+    write_emails(&counted(), &options, &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "bob@example.com\t3\nalice@example.com\t1\ncarol@example.com\t1\n"
+    );
+}
+
+#[test]
+fn writes_csv_with_files_column() {
+    let output = write(&counted(), Format::Csv);
+    let mut lines = output.lines();
+    assert_eq!(
+        lines.next(),
+        Some("address,normalized,count,path,member,page,line,offset,roles,files")
+    );
+    assert_eq!(
+        lines.nth(1),
+        Some("bob@example.com,bob@example.com,3,first.txt,,,1,18,,first.txt=2; second.txt=1")
+    );
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use common::{addresses, assert_survives_corruption, entry, extract, fixture};
+use email_address_extractor::{file::Limits, Extractor, Input, Role};
+use std::io::Cursor;
+
+/// Returns the addresses the fixture data files hold for each of their three messages.
+fn expected() -> Vec<String> {
+    let mut expected = Vec::new();
+    for field in [
+        "att", "bcc", "body", "cc", "end", "named", "orig", "sender", "to",
+    ] {
+        for message in ["a", "b", "c"] {
+            if field != "att" {
+                expected.push(format!("{}.{}.inner@example.com", field, message));
+            }
+            expected.push(format!("{}.{}@example.com", field, message));
+        }
+    }
+    expected.sort();
+    expected
+}
+
+#[test]
+fn extracts_messages_of_unicode_data_file() {
+    assert_eq!(addresses(&fixture("store.pst")), expected());
+}
+
+#[test]
+fn extracts_messages_of_ansi_data_file() {
+    assert_eq!(addresses(&fixture("ansi.pst")), expected());
+}
+
+#[test]
+fn names_folders_and_tags_recipients() {
+    let emails = extract(&fixture("store.pst")).unwrap();
+    let entry = |address| entry(&emails, address);
+    let cc = entry("cc.c@example.com");
+    assert!(cc.roles.contains(&Role::Cc));
+    assert_eq!(
+        cc.source.member.as_deref(),
+        Some("Top of Outlook data file/Sent Items/message 1")
+    );
+    assert_eq!(
+        entry("att.b@example.com").source.member.as_deref(),
+        Some("Top of Outlook data file/Inbox/message 2/notes.txt")
+    );
+    assert!(entry("to.a.inner@example.com")
+        .roles
+        .contains(&Role::Recipient));
+}
+
+#[test]
+fn survives_malformed_data_files() {
+    for name in ["store.pst", "ansi.pst"] {
+        assert_survives_corruption(&fixture(name));
+    }
+}
+
+#[test]
+fn counts_node_data_against_total_size() {
+    let limits = Limits {
+        max_total_size: 4096,
+        ..Limits::default()
+    };
+    let error = Extractor::new()
+        .with_limits(limits)
+        .extract_reader(Cursor::new(fixture("store.pst")), &Input::default())
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Total extracted size exceeds the limit of 4096 bytes"
+    );
+}
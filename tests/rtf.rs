@@ -0,0 +1,25 @@
+mod common;
+
+use common::{addresses, assert_survives_corruption, fixture};
+
+#[test]
+fn extracts_decoded_text_and_mailto_links() {
+    // Addresses in the font table, the generator group and pictures are dropped,
+    // and the escaped braces break the address in the text apart.
+    assert_eq!(
+        addresses(&fixture("doc.rtf")),
+        [
+            "alice@example.com",
+            "bob@example.org",
+            "carol@example.net",
+            "dave@example.com",
+            "frank@example.com"
+        ]
+    );
+}
+
+#[test]
+fn survives_malformed_documents() {
+    assert_survives_corruption(&fixture("doc.rtf"));
+    assert_survives_corruption(br"{\rtf1{{{{{{\u-99999999999?\'zz\bin99999999 ");
+}
@@ -0,0 +1,169 @@
+use email_address_extractor::{file::Limits, Emails, Extractor, Input, Order};
+use sevenz_rust::{
+    AesEncoderOptions, SevenZArchiveEntry, SevenZMethod, SevenZWriter, SourceReader,
+};
+use std::io;
+use std::io::Cursor;
+
+/// Builds a 7z archive with the given members, compressed together as one solid block or each on its own.
+fn sevenz(members: &[(&str, &[u8])], solid: bool) -> Vec<u8> {
+    write(
+        SevenZWriter::new(Cursor::new(Vec::new())).unwrap(),
+        members,
+        solid,
+    )
+}
+
+/// Builds a 7z archive with the given members encrypted with the password, optionally including the file names.
+fn encrypted_sevenz(members: &[(&str, &[u8])], password: &str, encrypt_header: bool) -> Vec<u8> {
+    let mut writer = SevenZWriter::new(Cursor::new(Vec::new())).unwrap();
+    writer.set_content_methods(vec![
+        AesEncoderOptions::new(password.into()).into(),
+        SevenZMethod::LZMA2.into(),
+    ]);
+    writer.set_encrypt_header(encrypt_header);
+    write(writer, members, false)
+}
+
+/// Writes the given members into the archive and finishes it.
+fn write(
+    mut writer: SevenZWriter<Cursor<Vec<u8>>>,
+    members: &[(&str, &[u8])],
+    solid: bool,
+) -> Vec<u8> {
+    let entry = |name: &str| {
+        let mut entry = SevenZArchiveEntry::new();
+        entry.name = name.to_string();
+        entry.has_stream = true;
+        entry
+    };
+    if solid {
+        let entries = members.iter().map(|(name, _)| entry(name)).collect();
+        let readers: Vec<_> = members
+            .iter()
+            .map(|(_, content)| SourceReader::new(*content))
+            .collect();
+        writer
+            .push_archive_entries(entries, readers.into())
+            .unwrap();
+    } else {
+        for (name, content) in members {
+            writer
+                .push_archive_entry(entry(name), Some(*content))
+                .unwrap();
+        }
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+/// Builds a highly compressible member of one MiB that ends with an email address.
+fn bomb() -> Vec<u8> {
+    let mut content = vec![b' '; 1024 * 1024];
+    content.extend_from_slice(b"bomb@example.com\n");
+    content
+}
+
+/// Extracts the members of the given archive and returns the found addresses with their member names.
+fn members(bytes: Vec<u8>, limits: Limits) -> Vec<(String, String)> {
+    Extractor::new()
+        .with_limits(limits)
+        .extract_reader(Cursor::new(bytes), &Input::default())
+        .unwrap()
+        .sorted(Order::FirstSeen)
+        .into_iter()
+        .map(|(email, entry)| {
+            (
+                email.clone(),
+                entry.source.member.as_deref().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn reads_members_of_archive() {
+    let archive = sevenz(
+        &[
+            ("a.txt", b"alice@example.com"),
+            ("b.csv", b"bob@example.com"),
+        ],
+        false,
+    );
+    assert_eq!(
+        members(archive, Limits::default()),
+        [
+            ("alice@example.com".to_string(), "a.txt".to_string()),
+            ("bob@example.com".to_string(), "b.csv".to_string())
+        ]
+    );
+}
+
+#[test]
+fn reads_members_of_solid_archive() {
+    let archive = sevenz(
+        &[
+            ("a.txt", b"alice@example.com"),
+            ("b.csv", b"bob@example.com"),
+        ],
+        true,
+    );
+    assert_eq!(
+        members(archive, Limits::default()),
+        [
+            ("alice@example.com".to_string(), "a.txt".to_string()),
+            ("bob@example.com".to_string(), "b.csv".to_string())
+        ]
+    );
+}
+
+#[test]
+fn limits_compression_ratio() {
+    let limits = Limits {
+        max_ratio: 100,
+        ..Limits::default()
+    };
+    for solid in [false, true] {
+        let bomb = bomb();
+        let archive = sevenz(&[("bomb.txt", &bomb), ("b.csv", b"bob@example.com")], solid);
+        assert_eq!(
+            members(archive, limits),
+            [("bob@example.com".to_string(), "b.csv".to_string())]
+        );
+    }
+}
+
+/// Extracts the addresses of the given archive with the given passwords.
+fn extract_with_passwords(bytes: Vec<u8>, passwords: &[&str]) -> io::Result<Emails> {
+    Extractor::new()
+        .with_passwords(passwords.iter().map(|password| password.to_string()))
+        .extract_reader(Cursor::new(bytes), &Input::default())
+}
+
+#[test]
+fn decrypts_encrypted_archive() {
+    for encrypt_header in [false, true] {
+        let archive =
+            encrypted_sevenz(&[("a.txt", b"alice@example.com")], "secret", encrypt_header);
+        let emails = extract_with_passwords(archive, &["wrong", "secret"]).unwrap();
+        assert!(emails.contains("alice@example.com"));
+    }
+}
+
+#[test]
+fn reports_locked_archive() {
+    for encrypt_header in [false, true] {
+        let archive =
+            encrypted_sevenz(&[("a.txt", b"alice@example.com")], "secret", encrypt_header);
+        for (passwords, message) in [
+            (&[][..], "Locked, no password given"),
+            (&["wrong"], "Locked, the password did not match"),
+            (
+                &["wrong", "other"],
+                "Locked, none of the 2 passwords matched",
+            ),
+        ] {
+            let error = extract_with_passwords(archive.clone(), passwords).unwrap_err();
+            assert_eq!(error.to_string(), message);
+        }
+    }
+}
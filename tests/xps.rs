@@ -0,0 +1,122 @@
+mod common;
+
+use common::{addresses, assert_survives_corruption, entry, extract};
+use std::io::{Cursor, Write};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+const SEQUENCE: &str = r#"<FixedDocumentSequence xmlns="http://schemas.microsoft.com/xps/2005/06">
+  <DocumentReference Source="/Documents/1/FixedDocument.fdoc"/>
+</FixedDocumentSequence>"#;
+
+const DOCUMENT: &str = r#"<FixedDocument xmlns="http://schemas.microsoft.com/xps/2005/06">
+  <PageContent Source="Pages/1.fpage"/>
+  <PageContent Source="Pages/2.fpage"/>
+</FixedDocument>"#;
+
+/// Wraps glyph runs and paths into the markup of a fixed page.
+fn page(content: &str) -> String {
+    format!(
+        r#"<FixedPage xmlns="http://schemas.microsoft.com/xps/2005/06" Width="816" Height="1056">{}</FixedPage>"#,
+        content
+    )
+}
+
+/// Builds a document with the given members besides the fixed document sequence and the fixed document.
+fn xps(pages: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    for (name, content) in [
+        ("FixedDocumentSequence.fdseq", SEQUENCE.as_bytes()),
+        ("Documents/1/FixedDocument.fdoc", DOCUMENT.as_bytes()),
+    ]
+    .into_iter()
+    .chain(pages.iter().copied())
+    {
+        writer.start_file(name, options).unwrap();
+        writer.write_all(content).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+/// Encodes the given text as UTF-16 with a little endian byte order mark.
+fn utf16(text: &str) -> Vec<u8> {
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+    bytes
+}
+
+#[test]
+fn numbers_pages_and_joins_glyph_runs() {
+    let first = page(
+        r#"<Glyphs OriginX="96" OriginY="100" UnicodeString="alice@exa"/>
+        <Glyphs OriginX="160" OriginY="100" UnicodeString="mple.com"/>
+        <Glyphs OriginX="96" OriginY="120" UnicodeString="{}{bob@example.com}"/>"#,
+    );
+    let second = page(
+        r#"<Glyphs OriginX="96" OriginY="100" UnicodeString="carol@example.com"/>
+        <Glyphs OriginX="96" OriginY="120" UnicodeString="dave@example"/>
+        <Glyphs OriginX="96" OriginY="140" UnicodeString=".com"/>"#,
+    );
+    let bytes = xps(&[
+        ("Documents/1/Pages/1.fpage", first.as_bytes()),
+        ("Documents/1/Pages/2.fpage", second.as_bytes()),
+    ]);
+    assert_eq!(
+        addresses(&bytes),
+        ["alice@example.com", "bob@example.com", "carol@example.com"]
+    );
+    let emails = extract(&bytes).unwrap();
+    assert_eq!(entry(&emails, "bob@example.com").source.page, Some(1));
+    assert_eq!(entry(&emails, "carol@example.com").source.page, Some(2));
+}
+
+#[test]
+fn extracts_mailto_links() {
+    let first = page(
+        r#"<Path FixedPage.NavigateUri="mailto:link%40example.com" Data="M 0,0 L 10,10"/>
+        <Path FixedPage.NavigateUri="https://example.com/web@example.com" Data="M 0,0 L 10,10"/>"#,
+    );
+    let bytes = xps(&[
+        ("Documents/1/Pages/1.fpage", first.as_bytes()),
+        ("Documents/1/Pages/2.fpage", page("").as_bytes()),
+    ]);
+    assert_eq!(addresses(&bytes), ["link@example.com"]);
+}
+
+#[test]
+fn decodes_utf16_pages() {
+    let markup = page(r#"<Glyphs OriginX="96" OriginY="100" UnicodeString="alice@example.com"/>"#);
+    let declared = format!(
+        r#"<?xml version="1.0" encoding="UTF-16"?>{}"#,
+        page(r#"<Glyphs OriginX="96" OriginY="100" UnicodeString="bob@example.com"/>"#)
+    );
+    // Without a byte order mark, the encoding is recognized by the start of the xml declaration.
+    let declared: Vec<u8> = declared.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let bytes = xps(&[
+        ("Documents/1/Pages/1.fpage", &utf16(&markup)),
+        ("Documents/1/Pages/2.fpage", &declared),
+    ]);
+    assert_eq!(addresses(&bytes), ["alice@example.com", "bob@example.com"]);
+}
+
+#[test]
+fn joins_pieces_of_interleaved_parts() {
+    let markup = page(r#"<Glyphs OriginX="96" OriginY="100" UnicodeString="alice@example.com"/>"#);
+    let (head, tail) = markup.split_at(markup.len() / 2);
+    let bytes = xps(&[
+        ("Documents/1/Pages/1.fpage/[0].piece", head.as_bytes()),
+        ("Documents/1/Pages/1.fpage/[1].last.piece", tail.as_bytes()),
+        ("Documents/1/Pages/2.fpage", page("").as_bytes()),
+    ]);
+    let emails = extract(&bytes).unwrap();
+    assert_eq!(entry(&emails, "alice@example.com").source.page, Some(1));
+}
+
+#[test]
+fn survives_malformed_documents() {
+    let first = page(r#"<Glyphs OriginX="96" OriginY="100" UnicodeString="alice@example.com"/>"#);
+    assert_survives_corruption(&xps(&[
+        ("Documents/1/Pages/1.fpage", first.as_bytes()),
+        ("Documents/1/Pages/2.fpage", &utf16(&page(""))),
+    ]));
+}